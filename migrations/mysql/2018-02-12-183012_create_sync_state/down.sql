@@ -0,0 +1,2 @@
+DROP TABLE sync_state;
+DROP TABLE pending_applications;
\ No newline at end of file
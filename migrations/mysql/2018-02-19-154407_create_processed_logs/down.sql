@@ -0,0 +1 @@
+DROP TABLE processed_logs;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE processed_logs DROP COLUMN previous_listing;
+DROP TABLE registry_listings;
\ No newline at end of file
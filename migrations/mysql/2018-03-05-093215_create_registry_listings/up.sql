@@ -0,0 +1,15 @@
+CREATE TABLE registry_listings (
+  listing_hash varchar(64) NOT NULL,
+  domain varchar(255) NOT NULL,
+  status varchar(16) NOT NULL,
+  owner varchar(40),
+  unstaked_deposit varchar(78) NOT NULL,
+  application_expiry bigint,
+  challenge_id varchar(78),
+  block_number bigint,
+  transaction_hash varchar(64),
+  PRIMARY KEY (listing_hash)
+);
+CREATE INDEX registry_listings_domain ON registry_listings (domain);
+ALTER TABLE processed_logs ADD COLUMN previous_listing text;
+-- run_migrations whitelists a registry listing for every listed domain, which needs keccak256 and cannot be done here
@@ -0,0 +1,29 @@
+CREATE TABLE challenges (
+  registry varchar(40) NOT NULL,
+  challenge_id varchar(78) NOT NULL,
+  listing_hash varchar(64) NOT NULL,
+  deposit varchar(78) NOT NULL,
+  poll_id varchar(78) NOT NULL,
+  data text NOT NULL,
+  outcome varchar(16) NOT NULL,
+  block_number bigint NOT NULL,
+  transaction_hash varchar(64) NOT NULL,
+  resolved_block_number bigint,
+  resolved_transaction_hash varchar(64),
+  PRIMARY KEY (registry, challenge_id)
+);
+CREATE INDEX challenges_listing_hash ON challenges (listing_hash);
+CREATE TABLE reward_claims (
+  id int NOT NULL AUTO_INCREMENT,
+  registry varchar(40) NOT NULL,
+  challenge_id varchar(78) NOT NULL,
+  voter varchar(40) NOT NULL,
+  reward varchar(78) NOT NULL,
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  transaction_hash varchar(64) NOT NULL,
+  PRIMARY KEY (id)
+);
+CREATE INDEX reward_claims_challenge_id ON reward_claims (registry, challenge_id);
+-- a log is recorded once, so a retried sync cannot add the same claim twice
+CREATE UNIQUE INDEX reward_claims_position ON reward_claims (registry, block_number, log_index);
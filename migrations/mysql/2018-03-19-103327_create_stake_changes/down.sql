@@ -0,0 +1 @@
+DROP TABLE stake_changes;
\ No newline at end of file
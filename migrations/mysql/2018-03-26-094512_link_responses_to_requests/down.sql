@@ -0,0 +1,5 @@
+ALTER TABLE responses
+  DROP FOREIGN KEY responses_request_id;
+ALTER TABLE responses
+  DROP COLUMN request_id,
+  DROP COLUMN ad_server;
@@ -0,0 +1,13 @@
+DROP INDEX requests_auction_id ON requests;
+ALTER TABLE requests
+  DROP COLUMN received_at,
+  DROP COLUMN auction_id,
+  DROP COLUMN impressions,
+  DROP COLUMN ad_format,
+  DROP COLUMN ad_size,
+  DROP COLUMN bid_floor_micros,
+  DROP COLUMN bid_floor_currency,
+  DROP COLUMN page_url,
+  DROP COLUMN device_type,
+  DROP COLUMN user_id_hash,
+  DROP COLUMN region;
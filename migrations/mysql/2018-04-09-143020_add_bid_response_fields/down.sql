@@ -0,0 +1,10 @@
+ALTER TABLE responses
+  DROP COLUMN seat,
+  DROP COLUMN bid_price_micros,
+  DROP COLUMN bid_currency,
+  DROP COLUMN creative_id,
+  DROP COLUMN deal_id,
+  DROP COLUMN latency_ms,
+  DROP COLUMN won,
+  DROP COLUMN clearing_price_micros,
+  DROP COLUMN loss_reason;
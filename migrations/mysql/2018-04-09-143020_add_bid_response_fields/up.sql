@@ -0,0 +1,11 @@
+-- won is empty until the win or loss notice arrives, prices are in millionths of the currency unit
+ALTER TABLE responses
+  ADD COLUMN seat varchar(64),
+  ADD COLUMN bid_price_micros bigint,
+  ADD COLUMN bid_currency varchar(3),
+  ADD COLUMN creative_id varchar(255),
+  ADD COLUMN deal_id varchar(255),
+  ADD COLUMN latency_ms int,
+  ADD COLUMN won boolean,
+  ADD COLUMN clearing_price_micros bigint,
+  ADD COLUMN loss_reason int;
@@ -0,0 +1,4 @@
+DROP INDEX requests_created_at ON requests;
+ALTER TABLE requests DROP COLUMN created_at;
+DROP INDEX responses_created_at ON responses;
+ALTER TABLE responses DROP COLUMN created_at;
@@ -0,0 +1,9 @@
+-- rows written before this migration are dated when they were received if that is known, or else when the migration ran
+-- datetime keeps the UTC time the bidder writes as it is, where timestamp is converted with the session time zone and ends in 2038
+-- the default is in the session time zone so the existing rows are dated again in UTC, new rows are always given their created_at
+ALTER TABLE requests ADD COLUMN created_at datetime(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6);
+UPDATE requests SET created_at = COALESCE(received_at, UTC_TIMESTAMP(6));
+CREATE INDEX requests_created_at ON requests (created_at);
+ALTER TABLE responses ADD COLUMN created_at datetime(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6);
+UPDATE responses SET created_at = UTC_TIMESTAMP(6);
+CREATE INDEX responses_created_at ON responses (created_at);
@@ -0,0 +1,3 @@
+DROP TABLE listings;
+DROP TABLE requests;
+DROP TABLE responses;
\ No newline at end of file
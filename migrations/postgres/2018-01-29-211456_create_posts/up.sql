@@ -0,0 +1,14 @@
+CREATE TABLE listings (
+  id SERIAL PRIMARY KEY,
+  domain varchar(255) NOT NULL
+);
+CREATE UNIQUE INDEX listings_domain ON listings (domain);
+CREATE TABLE requests (
+  id SERIAL PRIMARY KEY,
+  publisher varchar(255) NOT NULL,
+  userquality int NOT NULL
+);
+CREATE TABLE responses (
+  id SERIAL PRIMARY KEY,
+  publisher varchar(255) NOT NULL
+);
\ No newline at end of file
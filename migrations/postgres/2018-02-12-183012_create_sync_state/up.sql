@@ -0,0 +1,12 @@
+CREATE TABLE sync_state (
+  registry varchar(40) NOT NULL,
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  PRIMARY KEY (registry)
+);
+CREATE TABLE pending_applications (
+  registry varchar(40) NOT NULL,
+  listing_hash varchar(64) NOT NULL,
+  domain varchar(255) NOT NULL,
+  PRIMARY KEY (registry, listing_hash)
+);
@@ -0,0 +1,9 @@
+CREATE TABLE rejected_applications (
+  id SERIAL PRIMARY KEY,
+  registry varchar(40) NOT NULL,
+  listing_hash varchar(64) NOT NULL,
+  data text NOT NULL,
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  transaction_hash varchar(64) NOT NULL
+);
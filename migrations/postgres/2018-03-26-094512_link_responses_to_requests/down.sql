@@ -0,0 +1,4 @@
+DROP INDEX responses_request_id;
+ALTER TABLE responses
+  DROP COLUMN request_id,
+  DROP COLUMN ad_server;
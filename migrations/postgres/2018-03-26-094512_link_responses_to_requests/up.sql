@@ -0,0 +1,6 @@
+-- responses written before this migration answer no known request and keep both columns empty
+-- ad_server has no foreign key so a response keeps the ad server it came from after the listing is removed
+ALTER TABLE responses
+  ADD COLUMN request_id int REFERENCES requests (id) ON DELETE CASCADE,
+  ADD COLUMN ad_server varchar(255);
+CREATE INDEX responses_request_id ON responses (request_id);
@@ -0,0 +1,14 @@
+-- prices are kept in millionths of the currency unit so they stay exact
+ALTER TABLE requests
+  ADD COLUMN received_at timestamp,
+  ADD COLUMN auction_id varchar(255),
+  ADD COLUMN impressions int,
+  ADD COLUMN ad_format varchar(16),
+  ADD COLUMN ad_size varchar(32),
+  ADD COLUMN bid_floor_micros bigint,
+  ADD COLUMN bid_floor_currency varchar(3),
+  ADD COLUMN page_url text,
+  ADD COLUMN device_type int,
+  ADD COLUMN user_id_hash varchar(64),
+  ADD COLUMN region varchar(64);
+CREATE INDEX requests_auction_id ON requests (auction_id);
@@ -0,0 +1,4 @@
+DROP INDEX requests_created_at;
+ALTER TABLE requests DROP COLUMN created_at;
+DROP INDEX responses_created_at;
+ALTER TABLE responses DROP COLUMN created_at;
@@ -0,0 +1,6 @@
+-- rows written before this migration are dated when they were received if that is known, or else when the migration ran
+ALTER TABLE requests ADD COLUMN created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'utc');
+UPDATE requests SET created_at = received_at WHERE received_at IS NOT NULL;
+CREATE INDEX requests_created_at ON requests (created_at);
+ALTER TABLE responses ADD COLUMN created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'utc');
+CREATE INDEX responses_created_at ON responses (created_at);
@@ -0,0 +1,14 @@
+CREATE TABLE listings (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  domain varchar(255) NOT NULL,
+  UNIQUE (domain)
+);
+CREATE TABLE requests (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  publisher varchar(255) NOT NULL,
+  userquality int NOT NULL
+);
+CREATE TABLE responses (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  publisher varchar(255) NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,11 @@
+CREATE TABLE processed_logs (
+  registry varchar(40) NOT NULL,
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  block_hash varchar(64) NOT NULL,
+  transaction_hash varchar(64) NOT NULL,
+  listing_hash varchar(64) NOT NULL,
+  action varchar(32) NOT NULL,
+  domain varchar(255),
+  PRIMARY KEY (registry, block_number, log_index)
+);
@@ -0,0 +1 @@
+DROP TABLE rejected_applications;
\ No newline at end of file
@@ -0,0 +1,18 @@
+-- sqlite cannot drop a column so processed_logs is rebuilt without it
+ALTER TABLE processed_logs RENAME TO processed_logs_with_previous_listing;
+CREATE TABLE processed_logs (
+  registry varchar(40) NOT NULL,
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  block_hash varchar(64) NOT NULL,
+  transaction_hash varchar(64) NOT NULL,
+  listing_hash varchar(64) NOT NULL,
+  action varchar(32) NOT NULL,
+  domain varchar(255),
+  PRIMARY KEY (registry, block_number, log_index)
+);
+INSERT INTO processed_logs
+  SELECT registry, block_number, log_index, block_hash, transaction_hash, listing_hash, action, domain
+  FROM processed_logs_with_previous_listing;
+DROP TABLE processed_logs_with_previous_listing;
+DROP TABLE registry_listings;
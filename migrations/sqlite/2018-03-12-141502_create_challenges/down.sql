@@ -0,0 +1,2 @@
+DROP TABLE challenges;
+DROP TABLE reward_claims;
\ No newline at end of file
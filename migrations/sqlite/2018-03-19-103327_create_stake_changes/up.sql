@@ -0,0 +1,14 @@
+CREATE TABLE stake_changes (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  registry varchar(40) NOT NULL,
+  listing_hash varchar(64) NOT NULL,
+  kind varchar(16) NOT NULL,
+  amount varchar(78) NOT NULL,
+  new_total varchar(78) NOT NULL,
+  block_number bigint NOT NULL,
+  log_index bigint NOT NULL,
+  transaction_hash varchar(64) NOT NULL
+);
+CREATE INDEX stake_changes_listing_hash ON stake_changes (listing_hash);
+-- a log is recorded once, so a retried sync cannot add the same stake change twice
+CREATE UNIQUE INDEX stake_changes_position ON stake_changes (registry, block_number, log_index);
@@ -0,0 +1,11 @@
+-- sqlite cannot drop a column used in a foreign key so responses is rebuilt without them
+DROP INDEX responses_request_id;
+ALTER TABLE responses RENAME TO responses_with_requests;
+CREATE TABLE responses (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  publisher varchar(255) NOT NULL
+);
+INSERT INTO responses
+  SELECT id, publisher
+  FROM responses_with_requests;
+DROP TABLE responses_with_requests;
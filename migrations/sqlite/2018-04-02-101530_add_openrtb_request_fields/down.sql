@@ -0,0 +1,13 @@
+-- sqlite cannot drop a column so requests is rebuilt without them
+-- the copy is renamed rather than requests itself so the foreign key of responses keeps pointing at requests
+DROP INDEX requests_auction_id;
+CREATE TABLE requests_without_openrtb_fields (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  publisher varchar(255) NOT NULL,
+  userquality int NOT NULL
+);
+INSERT INTO requests_without_openrtb_fields
+  SELECT id, publisher, userquality
+  FROM requests;
+DROP TABLE requests;
+ALTER TABLE requests_without_openrtb_fields RENAME TO requests;
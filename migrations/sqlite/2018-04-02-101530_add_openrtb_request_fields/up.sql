@@ -0,0 +1,13 @@
+-- prices are kept in millionths of the currency unit so they stay exact
+ALTER TABLE requests ADD COLUMN received_at timestamp;
+ALTER TABLE requests ADD COLUMN auction_id varchar(255);
+ALTER TABLE requests ADD COLUMN impressions int;
+ALTER TABLE requests ADD COLUMN ad_format varchar(16);
+ALTER TABLE requests ADD COLUMN ad_size varchar(32);
+ALTER TABLE requests ADD COLUMN bid_floor_micros bigint;
+ALTER TABLE requests ADD COLUMN bid_floor_currency varchar(3);
+ALTER TABLE requests ADD COLUMN page_url text;
+ALTER TABLE requests ADD COLUMN device_type int;
+ALTER TABLE requests ADD COLUMN user_id_hash varchar(64);
+ALTER TABLE requests ADD COLUMN region varchar(64);
+CREATE INDEX requests_auction_id ON requests (auction_id);
@@ -0,0 +1,14 @@
+-- sqlite cannot drop a column so responses is rebuilt without them
+DROP INDEX responses_request_id;
+CREATE TABLE responses_without_bids (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  publisher varchar(255) NOT NULL,
+  request_id integer REFERENCES requests (id) ON DELETE CASCADE,
+  ad_server varchar(255)
+);
+INSERT INTO responses_without_bids
+  SELECT id, publisher, request_id, ad_server
+  FROM responses;
+DROP TABLE responses;
+ALTER TABLE responses_without_bids RENAME TO responses;
+CREATE INDEX responses_request_id ON responses (request_id);
@@ -0,0 +1,10 @@
+-- won is empty until the win or loss notice arrives, prices are in millionths of the currency unit
+ALTER TABLE responses ADD COLUMN seat varchar(64);
+ALTER TABLE responses ADD COLUMN bid_price_micros bigint;
+ALTER TABLE responses ADD COLUMN bid_currency varchar(3);
+ALTER TABLE responses ADD COLUMN creative_id varchar(255);
+ALTER TABLE responses ADD COLUMN deal_id varchar(255);
+ALTER TABLE responses ADD COLUMN latency_ms int;
+ALTER TABLE responses ADD COLUMN won boolean;
+ALTER TABLE responses ADD COLUMN clearing_price_micros bigint;
+ALTER TABLE responses ADD COLUMN loss_reason int;
@@ -0,0 +1,51 @@
+DROP INDEX requests_created_at;
+DROP INDEX responses_created_at;
+-- sqlite cannot drop a column so both tables are rebuilt without it
+-- the copies are renamed rather than the tables themselves so the foreign key of responses keeps pointing at requests
+CREATE TABLE requests_without_created_at (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  publisher varchar(255) NOT NULL,
+  userquality int NOT NULL,
+  received_at timestamp,
+  auction_id varchar(255),
+  impressions int,
+  ad_format varchar(16),
+  ad_size varchar(32),
+  bid_floor_micros bigint,
+  bid_floor_currency varchar(3),
+  page_url text,
+  device_type int,
+  user_id_hash varchar(64),
+  region varchar(64)
+);
+INSERT INTO requests_without_created_at
+  SELECT id, publisher, userquality, received_at, auction_id, impressions, ad_format, ad_size, bid_floor_micros,
+    bid_floor_currency, page_url, device_type, user_id_hash, region
+  FROM requests;
+DROP INDEX requests_auction_id;
+DROP TABLE requests;
+ALTER TABLE requests_without_created_at RENAME TO requests;
+CREATE INDEX requests_auction_id ON requests (auction_id);
+CREATE TABLE responses_without_created_at (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  publisher varchar(255) NOT NULL,
+  request_id integer REFERENCES requests (id) ON DELETE CASCADE,
+  ad_server varchar(255),
+  seat varchar(64),
+  bid_price_micros bigint,
+  bid_currency varchar(3),
+  creative_id varchar(255),
+  deal_id varchar(255),
+  latency_ms int,
+  won boolean,
+  clearing_price_micros bigint,
+  loss_reason int
+);
+INSERT INTO responses_without_created_at
+  SELECT id, publisher, request_id, ad_server, seat, bid_price_micros, bid_currency, creative_id, deal_id, latency_ms,
+    won, clearing_price_micros, loss_reason
+  FROM responses;
+DROP INDEX responses_request_id;
+DROP TABLE responses;
+ALTER TABLE responses_without_created_at RENAME TO responses;
+CREATE INDEX responses_request_id ON responses (request_id);
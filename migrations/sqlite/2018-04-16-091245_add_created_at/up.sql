@@ -0,0 +1,8 @@
+-- sqlite only adds columns with a constant default so the rows written before this migration are dated afterwards,
+-- when they were received if that is known, or else when the migration ran
+ALTER TABLE requests ADD COLUMN created_at timestamp NOT NULL DEFAULT '1970-01-01 00:00:00';
+UPDATE requests SET created_at = COALESCE(received_at, CURRENT_TIMESTAMP);
+CREATE INDEX requests_created_at ON requests (created_at);
+ALTER TABLE responses ADD COLUMN created_at timestamp NOT NULL DEFAULT '1970-01-01 00:00:00';
+UPDATE responses SET created_at = CURRENT_TIMESTAMP;
+CREATE INDEX responses_created_at ON responses (created_at);
@@ -0,0 +1,178 @@
+use std::cmp;
+use std::net::SocketAddr;
+use std::str;
+use std::sync::Arc;
+use std::thread;
+
+use serde_json::Value;
+use tiny_http::{Header, Method, Response, Server};
+
+use domain::DomainName;
+use error::Error;
+use super::{DbConnection, ConnectionPool, get_connection};
+use super::{get_whitelisted_domains, is_whitelisted, get_registry_listing_by_domain, get_listing_stake, challenges_for_domain};
+use super::{get_latest_request_id, get_latest_response_id};
+
+//answer to an API call before it is written out, the body is always JSON
+#[derive(Debug, PartialEq)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl ApiResponse {
+    fn ok(body: Value) -> ApiResponse {
+        ApiResponse {
+            status: 200,
+            body: body,
+        }
+    }
+
+    //invalid domains are the caller's fault, rows that do not exist are not found and everything else is on the server
+    fn from_error(e: &Error) -> ApiResponse {
+        let status = match *e {
+            Error::InvalidDomain(_) => 400,
+            Error::NotFound(_) => 404,
+            _ => 500,
+        };
+        ApiResponse {
+            status: status,
+            body: json!({ "error": e.to_string() }),
+        }
+    }
+}
+
+//the HTTP API listening in the background
+pub struct ApiServer {
+    address: SocketAddr,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl ApiServer {
+    //address the API was bound to, which tells the port when it was started on port 0
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    //blocks for as long as the API is serving
+    pub fn wait(self) {
+        for worker in self.workers {
+            let _ = worker.join();
+        }
+    }
+}
+
+//serves the API for the registry on address with threads requests answered at once, each on a connection borrowed from the pool
+pub fn serve(pool: &ConnectionPool, registry_address: &str, address: &str, threads: usize) -> Result<ApiServer, Error> {
+    let server = match Server::http(address) {
+        Ok(val) => Arc::new(val),
+        Err(e) => return Err(Error::Api(format!("Could not listen on {}: {}", address, e))),
+    };
+    let bound = server.server_addr();
+
+    let workers = (0..cmp::max(threads, 1)).map(|_| {
+        let server = server.clone();
+        let pool = pool.clone();
+        let registry_address = registry_address.to_string();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let response = match *request.method() {
+                    Method::Get => match get_connection(&pool) {
+                        Ok(conn) => handle(&conn, &registry_address, request.url()),
+                        Err(e) => ApiResponse::from_error(&e),
+                    },
+                    _ => ApiResponse {
+                        status: 405,
+                        body: json!({ "error": "Only GET is supported" }),
+                    },
+                };
+                let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("static header is valid");
+                let written = request.respond(Response::from_string(response.body.to_string())
+                    .with_status_code(response.status)
+                    .with_header(content_type));
+                if let Err(e) = written {
+                    println!("API response could not be written: {}", e);
+                }
+            }
+        })
+    }).collect();
+
+    Ok(ApiServer {
+        address: bound,
+        workers: workers,
+    })
+}
+
+//answers a GET of url for the registry, which may carry a query string that is ignored
+//GET /listings
+//GET /listings/{domain}
+//GET /whitelisted/{domain}
+//GET /requests/latest
+//GET /responses/latest
+pub fn handle(conn: &DbConnection, registry_address: &str, url: &str) -> ApiResponse {
+    let path = url.split('?').next().unwrap_or("");
+    let segments: Vec<String> = path.trim_matches('/').split('/').map(percent_decode).collect();
+    let segments: Vec<&str> = segments.iter().map(|segment| segment.as_str()).collect();
+
+    let result = match segments.as_slice() {
+        ["listings"] => listings(conn),
+        ["listings", domain_name] => listing(conn, registry_address, domain_name),
+        ["whitelisted", domain_name] => whitelisted(conn, domain_name),
+        ["requests", "latest"] => get_latest_request_id(conn).map(|id| json!({ "id": id })),
+        ["responses", "latest"] => get_latest_response_id(conn).map(|id| json!({ "id": id })),
+        _ => Err(Error::NotFound(format!("Endpoint {}", path))),
+    };
+    match result {
+        Ok(body) => ApiResponse::ok(body),
+        Err(e) => ApiResponse::from_error(&e),
+    }
+}
+
+fn listings(conn: &DbConnection) -> Result<Value, Error> {
+    let domains = get_whitelisted_domains(conn)?;
+    Ok(json!({ "listings": domains }))
+}
+
+//lifecycle status, stake and challenges of the listing
+fn listing(conn: &DbConnection, registry_address: &str, domain_name: &str) -> Result<Value, Error> {
+    let registry_listing = match get_registry_listing_by_domain(conn, domain_name)? {
+        Some(val) => val,
+        None => return Err(Error::NotFound(format!("Listing for {}", domain_name))),
+    };
+    let stake = get_listing_stake(conn, registry_address, domain_name)?;
+    let challenges = challenges_for_domain(conn, domain_name)?;
+    Ok(json!({
+        "listing": registry_listing,
+        "stake": stake,
+        "challenges": challenges,
+    }))
+}
+
+fn whitelisted(conn: &DbConnection, domain_name: &str) -> Result<Value, Error> {
+    let domain_name = DomainName::new(domain_name)?;
+    let whitelisted = is_whitelisted(conn, &domain_name)?;
+    Ok(json!({
+        "domain": domain_name.as_str(),
+        "whitelisted": whitelisted,
+    }))
+}
+
+//decodes %XX escapes so internationalized domains can be sent in the path, malformed escapes are kept as they are
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut position = 0;
+    while position < bytes.len() {
+        if bytes[position] == b'%' && position + 2 < bytes.len() {
+            let escape = str::from_utf8(&bytes[position + 1..position + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = escape {
+                decoded.push(byte);
+                position += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[position]);
+        position += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
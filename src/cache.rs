@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use domain::DomainName;
+use error::Error;
+use super::{DbConnection, ConnectionPool, get_connection, get_whitelisted_domains};
+
+//change to the listings table made by the registry sync
+#[derive(Clone, Debug, PartialEq)]
+pub enum WhitelistChange {
+    Added(DomainName),
+    Removed(DomainName),
+}
+
+//the whitelisted registry listings held in memory so the bidder can check a domain without a database round trip
+//it is shared between threads behind an Arc, lookups only take a read lock
+pub struct WhitelistCache {
+    domains: RwLock<HashSet<String>>,
+    //lookups answered with whitelisted
+    hits: AtomicUsize,
+    //lookups answered with not whitelisted
+    misses: AtomicUsize,
+}
+
+impl WhitelistCache {
+    pub fn new() -> WhitelistCache {
+        WhitelistCache {
+            domains: RwLock::new(HashSet::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    //creates the cache filled with every listing
+    pub fn load(conn: &DbConnection) -> Result<WhitelistCache, Error> {
+        let cache = WhitelistCache::new();
+        cache.refresh(conn)?;
+        Ok(cache)
+    }
+
+    //replaces the cached domains with the whitelisted registry listings and returns the number of listings
+    pub fn refresh(&self, conn: &DbConnection) -> Result<usize, Error> {
+        let loaded: HashSet<String> = get_whitelisted_domains(conn)?.into_iter().collect();
+        let count = loaded.len();
+        *self.domains.write().unwrap_or_else(|e| e.into_inner()) = loaded;
+        Ok(count)
+    }
+
+    pub fn apply(&self, change: &WhitelistChange) {
+        let mut domains = self.domains.write().unwrap_or_else(|e| e.into_inner());
+        match *change {
+            WhitelistChange::Added(ref name) => domains.insert(name.as_str().to_string()),
+            WhitelistChange::Removed(ref name) => domains.remove(name.as_str()),
+        };
+    }
+
+    pub fn is_whitelisted(&self, domain_name: &DomainName) -> bool {
+        let whitelisted = self.domains.read().unwrap_or_else(|e| e.into_inner()).contains(domain_name.as_str());
+        if whitelisted {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        whitelisted
+    }
+
+    pub fn len(&self) -> usize {
+        self.domains.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for WhitelistCache {
+    fn default() -> WhitelistCache {
+        WhitelistCache::new()
+    }
+}
+
+//applies the changes sent by the registry sync as they arrive and reloads the whole cache every refresh_interval,
+//which also picks up listings changed outside the sync, keeps going on periodic refreshes once the sender is dropped
+pub fn keep_up_to_date(cache: Arc<WhitelistCache>, pool: ConnectionPool, changes: Receiver<WhitelistChange>, refresh_interval: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut next_refresh = Instant::now() + refresh_interval;
+        let mut listening = true;
+        loop {
+            let now = Instant::now();
+            if now >= next_refresh {
+                let refreshed = get_connection(&pool).and_then(|conn| cache.refresh(&conn));
+                if let Err(e) = refreshed {
+                    println!("Whitelist cache could not be refreshed, keeping the cached listings: {}", e);
+                }
+                next_refresh = Instant::now() + refresh_interval;
+                continue;
+            }
+
+            let wait = next_refresh - now;
+            if !listening {
+                thread::sleep(wait);
+                continue;
+            }
+            match changes.recv_timeout(wait) {
+                Ok(change) => cache.apply(&change),
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => listening = false,
+            }
+        }
+    })
+}
@@ -0,0 +1,148 @@
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+
+use dotenv::dotenv;
+
+use toml;
+
+use error::Error;
+
+//settings for the registry sync, defaults are overridden by the config file, then the environment, then command line flags
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub rpc_url: String,
+    //hex address of the Registry contract without the 0x prefix
+    pub registry_address: String,
+    //first block read on a registry that has never been synced
+    pub start_block: u64,
+    //number of blocks a log must be buried under before it is applied to the database
+    pub confirmation_depth: u64,
+    //seconds between polls of the log filter
+    pub poll_interval: u64,
+    pub database_url: Option<String>,
+    //most connections the pool keeps open
+    pub pool_size: u32,
+    //seconds to wait for a connection from the pool
+    pub pool_timeout: u64,
+    //check connections with a query before lending them out
+    pub pool_health_check: bool,
+    //requests and responses older than this many days are deleted by retention
+    pub retention_days: u64,
+    //most rows retention deletes in one transaction
+    pub retention_chunk_size: i64,
+    //directory retention archives the rows it deletes to, they are not archived when it is not set
+    pub archive_dir: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            rpc_url: format!("http://localhost:8545"),
+            registry_address: format!("8009a230dc908e71befafba36e09efef2513640d"),
+            start_block: 0,
+            confirmation_depth: 12,
+            poll_interval: 1,
+            database_url: None,
+            pool_size: 10,
+            pool_timeout: 30,
+            pool_health_check: true,
+            retention_days: 90,
+            retention_chunk_size: 1000,
+            archive_dir: None,
+        }
+    }
+}
+
+impl Config {
+    //reads the config file, leaving out settings at their defaults
+    pub fn from_file(path: &str) -> Result<Config, Error> {
+        let mut contents = String::new();
+        if let Err(e) = File::open(path).and_then(|mut file| file.read_to_string(&mut contents)) {
+            return Err(Error::Config(format!("Could not read {}: {}", path, e)));
+        }
+        Config::from_toml(&contents)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Config, Error> {
+        match toml::from_str(contents) {
+            Ok(val) => Ok(val),
+            Err(e) => Err(Error::Config(format!("Config could not be parsed: {}", e))),
+        }
+    }
+
+    //defaults overridden by the config file at path if there is one and then by the environment and .env
+    pub fn load(path: Option<&str>) -> Result<Config, Error> {
+        let config = match path {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        config.with_env()
+    }
+
+    //overrides settings with RPC_URL, REGISTRY_ADDRESS, START_BLOCK, CONFIRMATION_DEPTH, POLL_INTERVAL, DATABASE_URL,
+    //POOL_SIZE, POOL_TIMEOUT, POOL_HEALTH_CHECK, RETENTION_DAYS, RETENTION_CHUNK_SIZE and ARCHIVE_DIR
+    pub fn with_env(mut self) -> Result<Config, Error> {
+        dotenv().ok();
+
+        if let Ok(val) = env::var("RPC_URL") {
+            self.rpc_url = val;
+        }
+        if let Ok(val) = env::var("REGISTRY_ADDRESS") {
+            self.registry_address = val;
+        }
+        if let Ok(val) = env::var("START_BLOCK") {
+            self.start_block = parse_setting("START_BLOCK", &val)?;
+        }
+        if let Ok(val) = env::var("CONFIRMATION_DEPTH") {
+            self.confirmation_depth = parse_setting("CONFIRMATION_DEPTH", &val)?;
+        }
+        if let Ok(val) = env::var("POLL_INTERVAL") {
+            self.poll_interval = parse_setting("POLL_INTERVAL", &val)?;
+        }
+        if let Ok(val) = env::var("DATABASE_URL") {
+            self.database_url = Some(val);
+        }
+        if let Ok(val) = env::var("POOL_SIZE") {
+            self.pool_size = parse_setting("POOL_SIZE", &val)?;
+        }
+        if let Ok(val) = env::var("POOL_TIMEOUT") {
+            self.pool_timeout = parse_setting("POOL_TIMEOUT", &val)?;
+        }
+        if let Ok(val) = env::var("POOL_HEALTH_CHECK") {
+            self.pool_health_check = parse_setting("POOL_HEALTH_CHECK", &val)?;
+        }
+        if let Ok(val) = env::var("RETENTION_DAYS") {
+            self.retention_days = parse_setting("RETENTION_DAYS", &val)?;
+        }
+        if let Ok(val) = env::var("RETENTION_CHUNK_SIZE") {
+            self.retention_chunk_size = parse_setting("RETENTION_CHUNK_SIZE", &val)?;
+        }
+        if let Ok(val) = env::var("ARCHIVE_DIR") {
+            self.archive_dir = Some(val);
+        }
+        Ok(self)
+    }
+
+    //the registry address without the 0x prefix
+    pub fn registry_address(&self) -> &str {
+        self.registry_address.trim_start_matches("0x")
+    }
+
+    pub fn database_url(&self) -> Result<&str, Error> {
+        match self.database_url {
+            Some(ref val) => Ok(val),
+            None => Err(Error::Config(format!("DATABASE_URL must be set"))),
+        }
+    }
+}
+
+//parses a numeric or boolean setting given as text on the command line or in the environment
+pub fn parse_setting<T: FromStr>(name: &str, value: &str) -> Result<T, Error> {
+    match value.parse() {
+        Ok(val) => Ok(val),
+        Err(_) => Err(Error::Config(format!("{} could not be parsed from {}", name, value))),
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+
+use serde_json;
+use tiny_keccak::keccak256;
+
+use web3::types::*;
+
+use error::Error;
+
+//ABI of the Registry contract checked in alongside the crate
+const REGISTRY_ABI: &str = include_str!("../Registry.json");
+
+//size in bytes of a single ABI encoded word
+const WORD: usize = 32;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct EventParam {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub indexed: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct EventAbi {
+    pub name: String,
+    pub inputs: Vec<EventParam>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct FunctionAbi {
+    pub name: String,
+    pub inputs: Vec<EventParam>,
+    pub outputs: Vec<EventParam>,
+}
+
+#[derive(Deserialize)]
+struct AbiEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    name: Option<String>,
+    inputs: Option<Vec<EventParam>>,
+    outputs: Option<Vec<EventParam>>,
+}
+
+impl EventAbi {
+    //canonical signature such as _Application(bytes32,uint256,string)
+    pub fn signature(&self) -> String {
+        let kinds: Vec<&str> = self.inputs.iter().map(|input| input.kind.as_str()).collect();
+        format!("{}({})", self.name, kinds.join(","))
+    }
+
+    //first topic of every log emitted for this event
+    pub fn topic(&self) -> H256 {
+        H256::from(keccak256(self.signature().as_bytes()))
+    }
+}
+
+impl FunctionAbi {
+    //canonical signature such as listings(bytes32)
+    pub fn signature(&self) -> String {
+        let kinds: Vec<&str> = self.inputs.iter().map(|input| input.kind.as_str()).collect();
+        format!("{}({})", self.name, kinds.join(","))
+    }
+
+    //first four bytes of the call data selecting this function
+    pub fn selector(&self) -> Vec<u8> {
+        keccak256(self.signature().as_bytes())[..4].to_vec()
+    }
+}
+
+//a single decoded ABI value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Address(H160),
+    Bool(bool),
+    FixedBytes(H256),
+    Uint(U256),
+    String(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Application {
+    pub listing_hash: H256,
+    pub deposit: U256,
+    pub data: String,
+}
+
+impl Application {
+    //the registry keys listings by keccak256 of the domain, any other data is not the listed domain
+    pub fn domain_matches_listing_hash(&self) -> bool {
+        H256::from(keccak256(self.data.as_bytes())) == self.listing_hash
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Challenge {
+    pub listing_hash: H256,
+    pub deposit: U256,
+    pub poll_id: U256,
+    pub data: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deposit {
+    pub listing_hash: H256,
+    pub added: U256,
+    pub new_total: U256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Withdrawal {
+    pub listing_hash: H256,
+    pub withdrew: U256,
+    pub new_total: U256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewListingWhitelisted {
+    pub listing_hash: H256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApplicationRemoved {
+    pub listing_hash: H256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListingRemoved {
+    pub listing_hash: H256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChallengeFailed {
+    pub challenge_id: U256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChallengeSucceeded {
+    pub challenge_id: U256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RewardClaimed {
+    pub voter: H160,
+    pub challenge_id: U256,
+    pub reward: U256,
+}
+
+//state of a listing as returned by the Registry listings(bytes32) getter
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListingState {
+    pub application_expiry: U256,
+    pub whitelisted: bool,
+    pub owner: H160,
+    pub unstaked_deposit: U256,
+    pub challenge_id: U256,
+}
+
+//every event emitted by the Registry contract
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegistryEvent {
+    Application(Application),
+    Challenge(Challenge),
+    Deposit(Deposit),
+    Withdrawal(Withdrawal),
+    NewListingWhitelisted(NewListingWhitelisted),
+    ApplicationRemoved(ApplicationRemoved),
+    ListingRemoved(ListingRemoved),
+    ChallengeFailed(ChallengeFailed),
+    ChallengeSucceeded(ChallengeSucceeded),
+    RewardClaimed(RewardClaimed),
+}
+
+impl RegistryEvent {
+    //listing hash the event refers to, challenge outcomes and rewards refer to a challenge instead
+    pub fn listing_hash(&self) -> Option<H256> {
+        match *self {
+            RegistryEvent::Application(ref event) => Some(event.listing_hash),
+            RegistryEvent::Challenge(ref event) => Some(event.listing_hash),
+            RegistryEvent::Deposit(ref event) => Some(event.listing_hash),
+            RegistryEvent::Withdrawal(ref event) => Some(event.listing_hash),
+            RegistryEvent::NewListingWhitelisted(ref event) => Some(event.listing_hash),
+            RegistryEvent::ApplicationRemoved(ref event) => Some(event.listing_hash),
+            RegistryEvent::ListingRemoved(ref event) => Some(event.listing_hash),
+            RegistryEvent::ChallengeFailed(_) => None,
+            RegistryEvent::ChallengeSucceeded(_) => None,
+            RegistryEvent::RewardClaimed(_) => None,
+        }
+    }
+}
+
+fn registry_abi() -> Result<Vec<AbiEntry>, Error> {
+    match serde_json::from_str(REGISTRY_ABI) {
+        Ok(val) => Ok(val),
+        Err(e) => Err(Error::Decode(format!("Registry ABI could not be parsed: {}", e))),
+    }
+}
+
+//returns every event described in the Registry ABI
+pub fn registry_events() -> Result<Vec<EventAbi>, Error> {
+    Ok(registry_abi()?.into_iter()
+        .filter(|entry| entry.kind == "event")
+        .filter_map(|entry| match (entry.name, entry.inputs) {
+            (Some(name), Some(inputs)) => Some(EventAbi { name: name, inputs: inputs }),
+            _ => None,
+        })
+        .collect())
+}
+
+//returns every function described in the Registry ABI
+pub fn registry_functions() -> Result<Vec<FunctionAbi>, Error> {
+    Ok(registry_abi()?.into_iter()
+        .filter(|entry| entry.kind == "function")
+        .filter_map(|entry| match (entry.name, entry.inputs) {
+            (Some(name), Some(inputs)) => Some(FunctionAbi { name: name, inputs: inputs, outputs: entry.outputs.unwrap_or_default() }),
+            _ => None,
+        })
+        .collect())
+}
+
+//decodes Registry logs into typed events using the topics computed from the ABI
+pub struct RegistryDecoder {
+    events: HashMap<H256, EventAbi>,
+    functions: HashMap<String, FunctionAbi>,
+}
+
+impl RegistryDecoder {
+    pub fn new() -> Result<RegistryDecoder, Error> {
+        let events = registry_events()?;
+        let functions = registry_functions()?;
+        Ok(RegistryDecoder {
+            events: events.into_iter().map(|event| (event.topic(), event)).collect(),
+            functions: functions.into_iter().map(|function| (function.name.clone(), function)).collect(),
+        })
+    }
+
+    //returns the ABI of the function with that name
+    pub fn function(&self, name: &str) -> Option<&FunctionAbi> {
+        self.functions.get(name)
+    }
+
+    //call data for the listings(bytes32) getter
+    pub fn encode_listings_call(&self, listing_hash: &H256) -> Result<Vec<u8>, Error> {
+        let function = match self.function("listings") {
+            Some(val) => val,
+            None => return Err(Error::Decode(format!("Registry ABI has no listings function."))),
+        };
+        let mut data = function.selector();
+        data.extend_from_slice(&listing_hash[..]);
+        Ok(data)
+    }
+
+    //decodes the return data of the listings(bytes32) getter
+    pub fn decode_listing(&self, data: &[u8]) -> Result<ListingState, Error> {
+        let function = match self.function("listings") {
+            Some(val) => val,
+            None => return Err(Error::Decode(format!("Registry ABI has no listings function."))),
+        };
+        let tokens = decode_params(&function.name, &function.outputs, &[], data)?;
+        Ok(ListingState {
+            application_expiry: uint(&function.name, &tokens, "applicationExpiry")?,
+            whitelisted: boolean(&function.name, &tokens, "whitelisted")?,
+            owner: address(&function.name, &tokens, "owner")?,
+            unstaked_deposit: uint(&function.name, &tokens, "unstakedDeposit")?,
+            challenge_id: uint(&function.name, &tokens, "challengeID")?,
+        })
+    }
+
+    //returns the ABI of the event with that name
+    pub fn event(&self, name: &str) -> Option<&EventAbi> {
+        self.events.values().find(|event| event.name == name)
+    }
+
+    pub fn decode(&self, log: &Log) -> Result<Option<RegistryEvent>, Error> {
+        self.decode_event(&log.topics, &log.data.0)
+    }
+
+    //returns None for logs whose first topic is not a Registry event
+    pub fn decode_event(&self, topics: &[H256], data: &[u8]) -> Result<Option<RegistryEvent>, Error> {
+        let abi = match topics.first().and_then(|topic| self.events.get(topic)) {
+            Some(val) => val,
+            None => return Ok(None),
+        };
+        let tokens = decode_params(&abi.name, &abi.inputs, &topics[1..], data)?;
+
+        let event = match abi.name.as_str() {
+            "_Application" => RegistryEvent::Application(Application {
+                listing_hash: fixed_bytes(&abi.name, &tokens, "listingHash")?,
+                deposit: uint(&abi.name, &tokens, "deposit")?,
+                data: string(&abi.name, &tokens, "data")?,
+            }),
+            "_Challenge" => RegistryEvent::Challenge(Challenge {
+                listing_hash: fixed_bytes(&abi.name, &tokens, "listingHash")?,
+                deposit: uint(&abi.name, &tokens, "deposit")?,
+                poll_id: uint(&abi.name, &tokens, "pollID")?,
+                data: string(&abi.name, &tokens, "data")?,
+            }),
+            "_Deposit" => RegistryEvent::Deposit(Deposit {
+                listing_hash: fixed_bytes(&abi.name, &tokens, "listingHash")?,
+                added: uint(&abi.name, &tokens, "added")?,
+                new_total: uint(&abi.name, &tokens, "newTotal")?,
+            }),
+            "_Withdrawal" => RegistryEvent::Withdrawal(Withdrawal {
+                listing_hash: fixed_bytes(&abi.name, &tokens, "listingHash")?,
+                withdrew: uint(&abi.name, &tokens, "withdrew")?,
+                new_total: uint(&abi.name, &tokens, "newTotal")?,
+            }),
+            "_NewListingWhitelisted" => RegistryEvent::NewListingWhitelisted(NewListingWhitelisted {
+                listing_hash: fixed_bytes(&abi.name, &tokens, "listingHash")?,
+            }),
+            "_ApplicationRemoved" => RegistryEvent::ApplicationRemoved(ApplicationRemoved {
+                listing_hash: fixed_bytes(&abi.name, &tokens, "listingHash")?,
+            }),
+            "_ListingRemoved" => RegistryEvent::ListingRemoved(ListingRemoved {
+                listing_hash: fixed_bytes(&abi.name, &tokens, "listingHash")?,
+            }),
+            "_ChallengeFailed" => RegistryEvent::ChallengeFailed(ChallengeFailed {
+                challenge_id: uint(&abi.name, &tokens, "challengeID")?,
+            }),
+            "_ChallengeSucceeded" => RegistryEvent::ChallengeSucceeded(ChallengeSucceeded {
+                challenge_id: uint(&abi.name, &tokens, "challengeID")?,
+            }),
+            "_RewardClaimed" => RegistryEvent::RewardClaimed(RewardClaimed {
+                voter: address(&abi.name, &tokens, "voter")?,
+                challenge_id: uint(&abi.name, &tokens, "challengeID")?,
+                reward: uint(&abi.name, &tokens, "reward")?,
+            }),
+            name => return Err(Error::Decode(format!("Registry event {} has no decoder.", name))),
+        };
+        Ok(Some(event))
+    }
+}
+
+//decodes the indexed parameters from the topics and the rest from the data per the Solidity ABI spec
+fn decode_params(context: &str, params: &[EventParam], topics: &[H256], data: &[u8]) -> Result<HashMap<String, Token>, Error> {
+    let mut tokens = HashMap::new();
+    let mut topics = topics.iter();
+    let mut head = 0;
+    for input in params {
+        let token = if input.indexed {
+            let topic = match topics.next() {
+                Some(val) => val,
+                None => return Err(Error::Decode(format!("{} is missing the topic for {}.", context, input.name))),
+            };
+            decode_static(&input.kind, topic)?
+        } else {
+            let word = read_word(data, head)?;
+            head += WORD;
+            if input.kind == "string" {
+                Token::String(decode_string(data, word_to_usize(word)?)?)
+            } else {
+                decode_static(&input.kind, word)?
+            }
+        };
+        tokens.insert(input.name.clone(), token);
+    }
+    Ok(tokens)
+}
+
+fn decode_static(kind: &str, word: &[u8]) -> Result<Token, Error> {
+    match kind {
+        "address" => Ok(Token::Address(H160::from(&word[12..WORD]))),
+        "bool" => Ok(Token::Bool(word[WORD - 1] != 0)),
+        "bytes32" => Ok(Token::FixedBytes(H256::from(word))),
+        "uint256" => Ok(Token::Uint(U256::from(word))),
+        _ => Err(Error::Decode(format!("ABI type {} is not supported.", kind))),
+    }
+}
+
+//dynamic strings are stored at offset as a length word followed by the padded bytes
+fn decode_string(data: &[u8], offset: usize) -> Result<String, Error> {
+    let length = word_to_usize(read_word(data, offset)?)?;
+    let start = offset + WORD;
+    let end = match start.checked_add(length) {
+        Some(val) if val <= data.len() => val,
+        _ => return Err(Error::Decode(format!("String of length {} runs past the end of the data.", length))),
+    };
+    match String::from_utf8(data[start..end].to_vec()) {
+        Ok(val) => Ok(val),
+        Err(_) => Err(Error::Decode(format!("String in the data is not valid UTF-8."))),
+    }
+}
+
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8], Error> {
+    match offset.checked_add(WORD) {
+        Some(end) if end <= data.len() => Ok(&data[offset..end]),
+        _ => Err(Error::Decode(format!("Data is too short to read a word at {}.", offset))),
+    }
+}
+
+//offsets and lengths are uint256 words that must fit in a usize
+fn word_to_usize(word: &[u8]) -> Result<usize, Error> {
+    if word[..WORD - 8].iter().any(|byte| *byte != 0) {
+        return Err(Error::Decode(format!("Offset or length in the data is too large.")));
+    }
+    Ok(word[WORD - 8..].iter().fold(0usize, |value, byte| (value << 8) | *byte as usize))
+}
+
+fn fixed_bytes(context: &str, tokens: &HashMap<String, Token>, name: &str) -> Result<H256, Error> {
+    match tokens.get(name) {
+        Some(&Token::FixedBytes(val)) => Ok(val),
+        _ => Err(Error::Decode(format!("{} has no bytes32 parameter {}.", context, name))),
+    }
+}
+
+fn uint(context: &str, tokens: &HashMap<String, Token>, name: &str) -> Result<U256, Error> {
+    match tokens.get(name) {
+        Some(&Token::Uint(val)) => Ok(val),
+        _ => Err(Error::Decode(format!("{} has no uint256 parameter {}.", context, name))),
+    }
+}
+
+fn address(context: &str, tokens: &HashMap<String, Token>, name: &str) -> Result<H160, Error> {
+    match tokens.get(name) {
+        Some(&Token::Address(val)) => Ok(val),
+        _ => Err(Error::Decode(format!("{} has no address parameter {}.", context, name))),
+    }
+}
+
+fn string(context: &str, tokens: &HashMap<String, Token>, name: &str) -> Result<String, Error> {
+    match tokens.get(name) {
+        Some(Token::String(val)) => Ok(val.clone()),
+        _ => Err(Error::Decode(format!("{} has no string parameter {}.", context, name))),
+    }
+}
+
+fn boolean(context: &str, tokens: &HashMap<String, Token>, name: &str) -> Result<bool, Error> {
+    match tokens.get(name) {
+        Some(&Token::Bool(val)) => Ok(val),
+        _ => Err(Error::Decode(format!("{} has no bool parameter {}.", context, name))),
+    }
+}
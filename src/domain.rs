@@ -0,0 +1,61 @@
+use std::fmt;
+use std::str::FromStr;
+
+use idna::uts46;
+
+use error::Error;
+
+//a domain name in the canonical form it is stored and looked up in
+//lowercase, without the trailing root dot and with internationalized labels converted to punycode (A-labels)
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DomainName(String);
+
+impl DomainName {
+    //validates the name against the label rules of RFC 1035 and the IDNA rules of RFC 5891
+    //every label has to be 1 to 63 letters, digits or hyphens, not starting or ending with a hyphen, and the name at most 253 characters
+    pub fn new(name: &str) -> Result<DomainName, Error> {
+        let name = name.strip_suffix('.').unwrap_or(name);
+        let flags = uts46::Flags {
+            use_std3_ascii_rules: true,
+            transitional_processing: false,
+            verify_dns_length: true,
+        };
+        let ascii = match uts46::to_ascii(name, flags) {
+            Ok(val) => val,
+            Err(_) => return Err(Error::InvalidDomain(name.to_string())),
+        };
+        //to_ascii lets through one more trailing dot and the characters of a label that are fine for IDNA but not DNS
+        if ascii.split('.').any(|label| label.is_empty() || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')) {
+            return Err(Error::InvalidDomain(name.to_string()));
+        }
+        Ok(DomainName(ascii))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl FromStr for DomainName {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<DomainName, Error> {
+        DomainName::new(name)
+    }
+}
+
+impl fmt::Display for DomainName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for DomainName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
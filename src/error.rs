@@ -0,0 +1,125 @@
+use std::error;
+use std::fmt;
+
+use diesel;
+use diesel::ConnectionError;
+use diesel::r2d2::PoolError;
+use diesel_migrations::RunMigrationsError;
+use diesel::result::DatabaseErrorKind;
+
+use web3;
+
+//every way a library call can fail
+#[derive(Debug)]
+pub enum Error {
+    //the domain name has characters that are not allowed in a domain
+    InvalidDomain(String),
+    //the domain is already listed
+    DuplicateListing(String),
+    //the row that was asked for does not exist
+    NotFound(String),
+    Database(diesel::result::Error),
+    Connection(ConnectionError),
+    //no connection could be borrowed from the pool in time
+    Pool(PoolError),
+    //the schema could not be brought up to date
+    Migration(RunMigrationsError),
+    ChainRpc(web3::Error),
+    //a log or call result did not match the Registry ABI, or an OpenRTB message could not be read
+    Decode(String),
+    //a setting is missing or could not be parsed
+    Config(String),
+    //the HTTP API could not be started
+    Api(String),
+    //rows could not be written to the archive before they were deleted
+    Archive(String),
+}
+
+impl Error {
+    //turns a unique constraint violation on the listings table into a duplicate listing
+    pub fn from_listing_insert(domain_name: &str, e: diesel::result::Error) -> Error {
+        match e {
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => Error::DuplicateListing(domain_name.to_string()),
+            e => Error::Database(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidDomain(ref name) => write!(f, "Improper domain name {}", name),
+            Error::DuplicateListing(ref name) => write!(f, "Listing for {} already exists", name),
+            Error::NotFound(ref what) => write!(f, "{} not found", what),
+            Error::Database(ref e) => write!(f, "Database error: {}", e),
+            Error::Connection(ref e) => write!(f, "Could not connect to the database: {}", e),
+            Error::Pool(ref e) => write!(f, "Could not get a connection from the pool: {}", e),
+            Error::Migration(ref e) => write!(f, "Could not run migrations: {}", e),
+            Error::ChainRpc(ref e) => write!(f, "Chain RPC error: {}", e),
+            Error::Decode(ref message) => write!(f, "Decode error: {}", message),
+            Error::Config(ref message) => write!(f, "Config error: {}", message),
+            Error::Api(ref message) => write!(f, "API error: {}", message),
+            Error::Archive(ref message) => write!(f, "Archive error: {}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidDomain(_) => "improper domain name",
+            Error::DuplicateListing(_) => "duplicate listing",
+            Error::NotFound(_) => "not found",
+            Error::Database(_) => "database error",
+            Error::Connection(_) => "connection error",
+            Error::Pool(_) => "connection pool error",
+            Error::Migration(_) => "migration error",
+            Error::ChainRpc(_) => "chain RPC error",
+            Error::Decode(_) => "decode error",
+            Error::Config(_) => "config error",
+            Error::Api(_) => "API error",
+            Error::Archive(_) => "archive error",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            Error::Database(ref e) => Some(e),
+            Error::Connection(ref e) => Some(e),
+            Error::Pool(ref e) => Some(e),
+            Error::Migration(ref e) => Some(e),
+            Error::ChainRpc(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<diesel::result::Error> for Error {
+    fn from(e: diesel::result::Error) -> Error {
+        Error::Database(e)
+    }
+}
+
+impl From<ConnectionError> for Error {
+    fn from(e: ConnectionError) -> Error {
+        Error::Connection(e)
+    }
+}
+
+impl From<PoolError> for Error {
+    fn from(e: PoolError) -> Error {
+        Error::Pool(e)
+    }
+}
+
+impl From<RunMigrationsError> for Error {
+    fn from(e: RunMigrationsError) -> Error {
+        Error::Migration(e)
+    }
+}
+
+impl From<web3::Error> for Error {
+    fn from(e: web3::Error) -> Error {
+        Error::ChainRpc(e)
+    }
+}
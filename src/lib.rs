@@ -1,358 +1,1123 @@
+extern crate chrono;
 #[macro_use]
 extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
 extern crate dotenv;
+extern crate flate2;
+extern crate idna;
+#[macro_use]
+extern crate lazy_static;
 extern crate rustc_hex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
 extern crate tiny_keccak;
+extern crate tiny_http;
+extern crate toml;
 extern crate web3;
 
 use diesel::prelude::*;
-use diesel::mysql::MysqlConnection;
 use diesel::sql_query;
+#[cfg(not(feature = "postgres"))]
+use diesel::sql_types::Text;
+use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel_migrations::MigrationConnection;
 
 use dotenv::dotenv;
 
+use chrono::{NaiveDateTime, Utc};
+
 use std::env;
-use std::str::FromStr;
 use std::time::Duration;
-use std::collections::HashMap;
-use std::sync::Mutex;
-
-use web3::types::*;
-use web3::futures::Future;
-use web3::api::EthFilter;
-use web3::api::Namespace;
-use web3::futures::Stream;
+use std::collections::{HashMap, HashSet};
 
 use rustc_hex::ToHex;
+use tiny_keccak::keccak256;
 
 use self::models::*;
+use self::suffix::PublicSuffixList;
+use self::openrtb::{BidRequest, BidResponse};
 
 pub mod schema;
 pub mod models;
+pub mod decoder;
+pub mod error;
+pub mod domain;
+pub mod suffix;
+pub mod cache;
+pub mod api;
+pub mod openrtb;
+pub mod config;
+mod sync;
+mod retention;
 
-pub fn current_auto_increment_value_responses(conn: &MysqlConnection) -> Result<i64, String> {
-    let response = sql_query("SELECT AUTO_INCREMENT FROM information_schema.TABLES WHERE TABLE_SCHEMA = \"acbidder_database\" AND TABLE_NAME = \"responses\"")
-        .get_results::<AutoIncrement>(conn);
-    let response = match response {
-        Ok(val) => val,
-        Err(_) => return Err(format!("AUTO_INCREMENT could not be retrieved from responses.")),
+pub use error::Error;
+pub use domain::DomainName;
+pub use cache::{WhitelistCache, WhitelistChange};
+pub use config::Config;
+
+#[cfg(any(all(feature = "mysql", feature = "sqlite"), all(feature = "mysql", feature = "postgres"), all(feature = "sqlite", feature = "postgres")))]
+compile_error!("only one of the mysql, sqlite and postgres features can be enabled, build the others with --no-default-features --features <backend>");
+
+//connection to the database backend picked by the mysql, sqlite or postgres feature
+#[cfg(feature = "mysql")]
+pub type DbConnection = diesel::mysql::MysqlConnection;
+#[cfg(feature = "sqlite")]
+pub type DbConnection = diesel::sqlite::SqliteConnection;
+#[cfg(feature = "postgres")]
+pub type DbConnection = diesel::pg::PgConnection;
+
+#[cfg(feature = "mysql")]
+embed_migrations!("migrations/mysql");
+#[cfg(feature = "sqlite")]
+embed_migrations!("migrations/sqlite");
+#[cfg(feature = "postgres")]
+embed_migrations!("migrations/postgres");
+
+//writes the row or overwrites the one with the same primary key
+#[cfg(not(feature = "postgres"))]
+macro_rules! upsert {
+    ($table:expr, $key:expr, $values:expr, $conn:expr) => {
+        diesel::replace_into($table).values($values).execute($conn)
     };
-    Ok(response[0].AUTO_INCREMENT)
 }
 
-pub fn current_auto_increment_value_requests(conn: &MysqlConnection) -> Result<i64, String> {
-    let response = sql_query("SELECT AUTO_INCREMENT FROM information_schema.TABLES WHERE TABLE_SCHEMA = \"acbidder_database\" AND TABLE_NAME = \"requests\"")
-        .get_results::<AutoIncrement>(conn);
-    let response = match response {
-        Ok(val) => val,
-        Err(_) => return Err(format!("AUTO_INCREMENT could not be retrieved from responses.")),
+//postgres has no REPLACE so the row is updated in place when the key already exists
+#[cfg(feature = "postgres")]
+macro_rules! upsert {
+    ($table:expr, $key:expr, $values:expr, $conn:expr) => {
+        diesel::insert_into($table).values($values).on_conflict($key).do_update().set($values).execute($conn)
+    };
+}
+
+//creates every table that does not exist yet, needed for fresh databases such as an in-memory sqlite one
+//then gives every listed domain without a registry listing one, which the migrations cannot do since SQL cannot hash a domain with keccak256
+pub fn run_migrations(conn: &DbConnection) -> Result<(), Error> {
+    embedded_migrations::run(conn)?;
+    backfill_registry_listings(conn)?;
+    Ok(())
+}
+
+//whitelists a registry listing for every domain in the listings table that has none and returns how many were added
+//these are the listings from before registry listings were kept, they are keyed by the pending application of the domain
+//so later logs of the registry find them, or by the hash of the domain as added by hand
+fn backfill_registry_listings(conn: &DbConnection) -> Result<usize, Error> {
+    use schema::{listings, pending_applications, registry_listings};
+
+    let known: HashSet<String> = registry_listings::table
+        .select(registry_listings::domain)
+        .load::<String>(conn)?
+        .into_iter()
+        .collect();
+    let listed: Vec<String> = listings::table
+        .select(listings::domain)
+        .load::<String>(conn)?
+        .into_iter()
+        .filter(|listed| !known.contains(listed))
+        .collect();
+    if listed.is_empty() {
+        return Ok(0);
+    }
+
+    let mut applied = HashMap::new();
+    for application in pending_applications::table.load::<PendingApplication>(conn)? {
+        if let Ok(name) = DomainName::new(&application.domain) {
+            applied.insert(name.into_string(), application.listing_hash);
+        }
+    }
+    let mut backfilled = 0;
+    for listed in listed {
+        let hash = match applied.get(&listed) {
+            Some(val) => val.clone(),
+            None => domain_listing_hash(&listed),
+        };
+        let listing = RegistryListing::new(&hash, &listed, ListingStatus::Whitelisted);
+        backfilled += upsert!(registry_listings::table, registry_listings::listing_hash, &listing, conn)?;
+    }
+    Ok(backfilled)
+}
+
+//versions of the migrations already run on the database, oldest first
+pub fn applied_migrations(conn: &DbConnection) -> Result<Vec<String>, Error> {
+    diesel_migrations::setup_database(conn)?;
+    let mut versions: Vec<String> = conn.previously_run_migration_versions()?.into_iter().collect();
+    versions.sort();
+    Ok(versions)
+}
+
+//id the next row inserted into the table will get
+#[cfg(feature = "mysql")]
+fn next_auto_increment(conn: &DbConnection, table_name: &str) -> Result<i64, Error> {
+    let mut response = sql_query("SELECT AUTO_INCREMENT FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?")
+        .bind::<Text, _>(table_name)
+        .get_results::<AutoIncrement>(conn)?;
+    match response.pop() {
+        Some(val) => Ok(val.AUTO_INCREMENT),
+        None => Err(Error::NotFound(format!("AUTO_INCREMENT of {}", table_name))),
+    }
+}
+
+//id the next row inserted into the table will get
+//sqlite only keeps a sequence for tables that have had a row inserted
+#[cfg(feature = "sqlite")]
+fn next_auto_increment(conn: &DbConnection, table_name: &str) -> Result<i64, Error> {
+    let mut response = sql_query("SELECT seq + 1 AS AUTO_INCREMENT FROM sqlite_sequence WHERE name = ?")
+        .bind::<Text, _>(table_name)
+        .get_results::<AutoIncrement>(conn)?;
+    match response.pop() {
+        Some(val) => Ok(val.AUTO_INCREMENT),
+        None => Ok(1),
+    }
+}
+
+//id the next row inserted into the table will get, read from the sequence behind its SERIAL id
+//last_value is the first id until the sequence has been called
+#[cfg(feature = "postgres")]
+fn next_auto_increment(conn: &DbConnection, table_name: &str) -> Result<i64, Error> {
+    let mut response = sql_query(format!("SELECT CASE WHEN is_called THEN last_value + 1 ELSE last_value END AS AUTO_INCREMENT FROM {}_id_seq", table_name))
+        .get_results::<AutoIncrement>(conn)?;
+    match response.pop() {
+        Some(val) => Ok(val.AUTO_INCREMENT),
+        None => Err(Error::NotFound(format!("sequence of {}", table_name))),
+    }
+}
+
+//id given to the last row inserted on this connection, other connections inserting at the same time do not change it
+#[cfg(feature = "mysql")]
+fn last_insert_id(conn: &DbConnection) -> Result<i32, Error> {
+    let inserted = sql_query("SELECT CAST(LAST_INSERT_ID() AS SIGNED) AS id").get_result::<LastInsertId>(conn)?;
+    Ok(inserted.id as i32)
+}
+
+//id given to the last row inserted on this connection, other connections inserting at the same time do not change it
+#[cfg(feature = "sqlite")]
+fn last_insert_id(conn: &DbConnection) -> Result<i32, Error> {
+    let inserted = sql_query("SELECT last_insert_rowid() AS id").get_result::<LastInsertId>(conn)?;
+    Ok(inserted.id as i32)
+}
+
+//inserts the row and returns the id it was given
+#[cfg(not(feature = "postgres"))]
+macro_rules! insert_returning_id {
+    ($table:expr, $id:expr, $values:expr, $conn:expr) => {
+        $conn.transaction::<i32, Error, _>(|| {
+            diesel::insert_into($table).values($values).execute($conn)?;
+            last_insert_id($conn)
+        })
+    };
+}
+
+//inserts the row and returns the id it was given
+#[cfg(feature = "postgres")]
+macro_rules! insert_returning_id {
+    ($table:expr, $id:expr, $values:expr, $conn:expr) => {
+        diesel::insert_into($table).values($values).returning($id).get_result::<i32>($conn).map_err(Error::from)
     };
-    Ok(response[0].AUTO_INCREMENT)
 }
 
-//TODO: modify to pass up error vs panicking
-pub fn establish_connection() -> MysqlConnection {
+pub fn current_auto_increment_value_responses(conn: &DbConnection) -> Result<i64, Error> {
+    next_auto_increment(conn, "responses")
+}
+
+pub fn current_auto_increment_value_requests(conn: &DbConnection) -> Result<i64, Error> {
+    next_auto_increment(conn, "requests")
+}
+
+//pool of database connections shared by the registry sync and the bidder
+pub type ConnectionPool = Pool<ConnectionManager<DbConnection>>;
+
+//connection borrowed from the pool, it goes back to the pool when dropped
+pub type PooledDbConnection = PooledConnection<ConnectionManager<DbConnection>>;
+
+//opens a single connection to DATABASE_URL
+pub fn establish_connection() -> Result<DbConnection, Error> {
     dotenv().ok();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    MysqlConnection::establish(&database_url)
-        .expect(&format!("Error connection to {}", database_url))
+    let database_url = match env::var("DATABASE_URL") {
+        Ok(val) => val,
+        Err(_) => return Err(Error::Config(format!("DATABASE_URL must be set"))),
+    };
+    Ok(DbConnection::establish(&database_url)?)
+}
+
+//creates a pool of connections to the configured database
+//connections are opened lazily so a database that is down surfaces as an error when borrowing instead
+pub fn create_pool(config: &Config) -> Result<ConnectionPool, Error> {
+    let manager = ConnectionManager::<DbConnection>::new(config.database_url()?);
+    Ok(Pool::builder()
+        .max_size(config.pool_size)
+        .connection_timeout(Duration::from_secs(config.pool_timeout))
+        .test_on_check_out(config.pool_health_check)
+        .build_unchecked(manager))
+}
+
+//borrows a connection from the pool, waiting up to the pool timeout for one to free up
+pub fn get_connection(pool: &ConnectionPool) -> Result<PooledDbConnection, Error> {
+    Ok(pool.get()?)
 }
 
+//true if the name is not a valid domain name, see DomainName for the rules
 pub fn improper_domain_name(name: & str) -> bool {
-    for character in name.chars() {
-        if !character.is_ascii_alphanumeric() && character != '.' && character != '-' {
+    DomainName::new(name).is_err()
+}
+
+//wildcard patterns may only use % and _ besides the characters allowed in a domain
+fn improper_domain_pattern(pattern: &str) -> bool {
+    for character in pattern.chars() {
+        if !character.is_ascii_alphanumeric() && character != '.' && character != '-' && character != '%' && character != '_' {
             return true;
         }
     }
     false
 }
 
-//returns number of listings created
-pub fn create_listing<'a>(conn: &MysqlConnection, domain_name: &'a str) -> Result<usize, String> {
-    use schema::listings;
+//listing hash the registry keys the domain under
+pub fn domain_listing_hash(domain_name: &str) -> String {
+    keccak256(domain_name.as_bytes()).to_hex()
+}
+
+//keeps the registry listing with the hash in step with the listings table, other listings of the same domain are left alone
+fn set_listing_status(conn: &DbConnection, hash: &str, domain_name: &DomainName, new_status: ListingStatus) -> QueryResult<usize> {
+    use schema::registry_listings::dsl::*;
+
+    let listing = match registry_listings.filter(listing_hash.eq(hash)).first::<RegistryListing>(conn).optional()? {
+        Some(mut val) => {
+            val.status = new_status.as_str().to_string();
+            val
+        }
+        None => RegistryListing::new(hash, domain_name.as_str(), new_status),
+    };
+    upsert!(registry_listings, listing_hash, &listing, conn)
+}
 
-    //ensures that no special characters are used and valid domain name characters are used
-    if improper_domain_name(domain_name) {
-        return Err(format!("Improper domain name {}", domain_name));
+//deletes the domain from the listings table unless a whitelisted registry listing other than the one with the hash has the domain
+//applications are keyed by the hash of the domain as it was written in them, so differently written applications can share a domain
+fn delete_unshared_listing(conn: &DbConnection, domain_name: &DomainName, hash: &str) -> QueryResult<usize> {
+    use schema::{listings, registry_listings};
+
+    let others = registry_listings::table
+        .filter(registry_listings::domain.eq(domain_name.as_str()))
+        .filter(registry_listings::status.eq(ListingStatus::Whitelisted.as_str()))
+        .filter(registry_listings::listing_hash.ne(hash))
+        .count()
+        .get_result::<i64>(conn)?;
+    if others > 0 {
+        return Ok(0);
     }
+    diesel::delete(listings::table.filter(listings::domain.eq(domain_name.as_str()))).execute(conn)
+}
+
+//returns number of listings created, a domain added by hand is keyed by the hash of its name
+pub fn create_listing(conn: &DbConnection, domain_name: &DomainName) -> Result<usize, Error> {
+    create_registry_listing_domain(conn, domain_name, &domain_listing_hash(domain_name.as_str()))
+}
+
+//returns number of listings created for the registry listing with the hash, which is whitelisted with it
+pub fn create_registry_listing_domain(conn: &DbConnection, domain_name: &DomainName, hash: &str) -> Result<usize, Error> {
+    use schema::listings;
 
     let new_ad_server = NewAdServer {
-        domain: domain_name,
+        domain: domain_name.as_str(),
     };
-    match diesel::insert_into(listings::table)
-        .values(&new_ad_server)
-        .execute(conn)
-    {
-        Ok(val) => return Ok(val),
-        Err(_) => return Err(format!("Insert for Listing failed.")),
-    }
+    conn.transaction::<usize, diesel::result::Error, _>(|| {
+        let created = diesel::insert_into(listings::table)
+            .values(&new_ad_server)
+            .execute(conn)?;
+        set_listing_status(conn, hash, domain_name, ListingStatus::Whitelisted)?;
+        Ok(created)
+    }).map_err(|e| Error::from_listing_insert(domain_name.as_str(), e))
 }
 
-pub fn is_whitelisted(conn: &MysqlConnection, domain_name: String) -> Result<bool, String> {
-    use schema::listings::dsl::*;
+//whether the registry listing of the domain is whitelisted, the same answer get_whitelisted_domains and a WhitelistCache give
+pub fn is_whitelisted(conn: &DbConnection, domain_name: &DomainName) -> Result<bool, Error> {
+    use schema::registry_listings::dsl::*;
+
+    let whitelisted = registry_listings
+        .filter(domain.eq(domain_name.as_str()))
+        .filter(status.eq(ListingStatus::Whitelisted.as_str()))
+        .first::<RegistryListing>(conn)
+        .optional()?;
+    Ok(whitelisted.is_some())
+}
 
-    if improper_domain_name(&domain_name) {
-        return Err(format!("Improper domain name {}", domain_name));
+//finds the whitelisted listing the host is served under, walking up from the host to its registrable domain
+//the most specific listing wins and nothing above the registrable domain is matched, so a listing for a public suffix
+//such as co.uk or a hosting platform such as github.io does not whitelist every site under it
+pub fn is_whitelisted_host(conn: &DbConnection, host: &str) -> Result<Option<HostMatch>, Error> {
+    use schema::registry_listings::dsl::*;
+
+    let host = DomainName::new(host)?;
+    let registrable = PublicSuffixList::bundled().registrable_domain(&host);
+
+    //the host first and then each parent domain down to the registrable domain
+    let mut candidates = vec![host.as_str()];
+    if let Some(ref registrable) = registrable {
+        let mut parent = host.as_str();
+        while parent != registrable.as_str() {
+            parent = &parent[parent.find('.').unwrap_or(0) + 1..];
+            candidates.push(parent);
+        }
     }
 
-    match listings
-        .filter(domain.like(domain_name))
-        .limit(1)
-        .load::<AdServer>(conn)
-    {
-        Ok(val) => {
-            if val.len() == 0 {
-                return Ok(false);
-            } else if val.len() > 1 {
-                return Err(format!("Something impossible happened."));
+    let whitelisted = registry_listings
+        .filter(domain.eq_any(&candidates))
+        .filter(status.eq(ListingStatus::Whitelisted.as_str()))
+        .load::<RegistryListing>(conn)?;
+    for (position, candidate) in candidates.iter().enumerate() {
+        if let Some(listing) = whitelisted.iter().find(|listing| listing.domain == *candidate) {
+            let reason = if position == 0 {
+                HostMatchReason::Exact
+            } else if registrable.as_ref().map(|val| val.as_str()) == Some(*candidate) {
+                HostMatchReason::RegistrableDomain
             } else {
-                return Ok(true);
-            }
+                HostMatchReason::ParentDomain
+            };
+            return Ok(Some(HostMatch {
+                host: host.clone(),
+                listing: listing.clone(),
+                reason: reason,
+            }));
         }
-        Err(e) => return Err(format!("Could not check if whitelisted: {}", e)),
     }
+    Ok(None)
+}
+
+//delets the listing (ad_server) named domain_name and returns the number of rows deleted, which is never more than one
+//the registry listing keyed by the hash of the name is removed with it, nothing is deleted while another whitelisted registry listing has the domain
+pub fn delete_listing(conn: &DbConnection, domain_name: String) -> Result<usize, Error> {
+    let domain_name = DomainName::new(&domain_name)?;
+    let hash = domain_listing_hash(domain_name.as_str());
+    let deleted = conn.transaction::<usize, diesel::result::Error, _>(|| {
+        let deleted = delete_unshared_listing(conn, &domain_name, &hash)?;
+        if deleted > 0 {
+            set_listing_status(conn, &hash, &domain_name, ListingStatus::Removed)?;
+        }
+        Ok(deleted)
+    })?;
+    Ok(deleted)
+}
+
+//deletes the listing of the registry listing with the hash and returns the number of rows deleted, none while another whitelisted registry listing has the domain
+//the status of the registry listing is left to the caller
+pub fn delete_registry_listing_domain(conn: &DbConnection, domain_name: String, hash: &str) -> Result<usize, Error> {
+    let domain_name = DomainName::new(&domain_name)?;
+    Ok(delete_unshared_listing(conn, &domain_name, hash)?)
+}
+
+//returns the domain of every whitelisted registry listing ordered by domain, which are the domains is_whitelisted accepts
+//this is the whitelist every reader uses, the listings table only mirrors it for the foreign key of responses
+pub fn get_whitelisted_domains(conn: &DbConnection) -> Result<Vec<String>, Error> {
+    use schema::registry_listings::dsl::*;
+
+    Ok(registry_listings
+        .filter(status.eq(ListingStatus::Whitelisted.as_str()))
+        .select(domain)
+        .distinct()
+        .order(domain.asc())
+        .load::<String>(conn)?)
+}
+
+//returns every listing ordered by domain
+pub fn get_listings(conn: &DbConnection) -> Result<Vec<AdServer>, Error> {
+    use schema::listings::dsl::*;
+
+    Ok(listings.order(domain.asc()).load::<AdServer>(conn)?)
 }
 
-//delets a listing (ad_server) with the name in domain_name and returns the number of rows deleted
-pub fn delete_listing(conn: &MysqlConnection, domain_name: String) -> Result<usize, String> {
+//returns the listings matching a LIKE pattern where % matches any run of characters and _ any single character
+pub fn search_listings(conn: &DbConnection, pattern: &str) -> Result<Vec<AdServer>, Error> {
     use schema::listings::dsl::*;
 
-    match diesel::delete(listings.filter(domain.like(domain_name))).execute(conn) {
-        Ok(val) => return Ok(val),
-        Err(_) => return Err(format!("Delete for Listing failed.")),
+    if improper_domain_pattern(pattern) {
+        return Err(Error::InvalidDomain(pattern.to_string()));
     }
+
+    Ok(listings
+        .filter(domain.like(pattern.trim_end_matches('.').to_ascii_lowercase()))
+        .order(domain.asc())
+        .load::<AdServer>(conn)?)
+}
+
+//records a request received now and returns the id number
+pub fn create_request(conn: &DbConnection, publisher_name: &DomainName, user_quality: i32) -> Result<i32, Error> {
+    let details = RequestDetails {
+        received_at: Some(Utc::now().naive_utc()),
+        ..RequestDetails::default()
+    };
+    create_request_with_details(conn, publisher_name, user_quality, &details)
 }
 
 //returns the id number
-pub fn create_request<'a>(conn: &MysqlConnection, publisher_name: &'a str, user_quality: i32) -> Result<i32, String> {
+pub fn create_request_with_details(conn: &DbConnection, publisher_name: &DomainName, user_quality: i32, details: &RequestDetails) -> Result<i32, Error> {
     use schema::requests;
-    use schema::requests::dsl::*;
-
-    if improper_domain_name(publisher_name) {
-        return Err(format!("Improper domain name {}", publisher_name));
-    }
 
     let new_request = NewRequest {
-        publisher: publisher_name,
+        publisher: publisher_name.as_str(),
         userquality: user_quality,
+        received_at: details.received_at,
+        auction_id: details.auction_id.as_deref(),
+        impressions: details.impressions,
+        ad_format: details.ad_format.as_deref(),
+        ad_size: details.ad_size.as_deref(),
+        bid_floor_micros: details.bid_floor_micros,
+        bid_floor_currency: details.bid_floor_currency.as_deref(),
+        page_url: details.page_url.as_deref(),
+        device_type: details.device_type,
+        user_id_hash: details.user_id_hash.as_deref(),
+        region: details.region.as_deref(),
+        created_at: Utc::now().naive_utc(),
     };
+    insert_returning_id!(requests::table, requests::id, &new_request, conn)
+}
 
-    match diesel::insert_into(requests::table)
-        .values(&new_request)
-        .execute(conn)
-    {
-            Ok(val) => val,
-            Err(_) => return Err(format!("Insert for Request has failed.")),
-    };
-    let request_inserted = match requests
-        .filter(publisher.like(publisher_name))
-        .limit(1)
-        .load::<Request>(conn)
-        {
-            Ok(val) => val,
-            Err(_) => return Err(format!("Could not retrieve id of newly added Request.")),
-        };
+//parses an OpenRTB 2.5 BidRequest, records it as received now and returns the id number
+pub fn ingest_openrtb_request(conn: &DbConnection, json: &str) -> Result<i32, Error> {
+    let bid_request = BidRequest::parse(json)?;
+    let publisher_name = bid_request.publisher()?;
+    let details = bid_request.details(Utc::now().naive_utc());
+    create_request_with_details(conn, &publisher_name, bid_request.user_quality(), &details)
+}
 
-    Ok(request_inserted[0].id)
+//returns the latest request for the auction or None if there is none
+pub fn get_request_by_auction_id(conn: &DbConnection, auction: &str) -> Result<Option<Request>, Error> {
+    use schema::requests::dsl::*;
+
+    Ok(requests
+        .filter(auction_id.eq(auction))
+        .order(id.desc())
+        .first::<Request>(conn)
+        .optional()?)
 }
 
-//return the id of the last request made or return 0
-pub fn get_latest_request_id(conn:&MysqlConnection) -> Result<i32, String> {
+//returns the request with the id or None if there is none
+pub fn get_request(conn: &DbConnection, request: i32) -> Result<Option<Request>, Error> {
     use schema::requests::dsl::*;
 
-    let latest_request = match requests
+    Ok(requests
+        .find(request)
+        .first::<Request>(conn)
+        .optional()?)
+}
+
+//takes publisher and user quality pairs and returns the id numbers in the order the requests were given
+pub fn create_requests(conn: &DbConnection, new_requests: &[(DomainName, i32)]) -> Result<Vec<i32>, Error> {
+    conn.transaction(|| {
+        let mut ids = Vec::with_capacity(new_requests.len());
+        for &(ref publisher_name, user_quality) in new_requests {
+            ids.push(create_request(conn, publisher_name, user_quality)?);
+        }
+        Ok(ids)
+    })
+}
+
+//return the id of the last request made
+pub fn get_latest_request_id(conn:&DbConnection) -> Result<i32, Error> {
+    use schema::requests::dsl::*;
+
+    let latest_request = requests
         .order(id.desc())
-        .limit(1)
-        .load::<Request>(conn)
-        {
-            Ok(val) => val,
-            Err(_) => return Err(format!("Could not retrieve id for Request.")),
-        };
-    Ok(latest_request[0].id)
+        .first::<Request>(conn)
+        .optional()?;
+    match latest_request {
+        Some(val) => Ok(val.id),
+        None => Err(Error::NotFound(format!("Request"))),
+    }
+}
+
+//delete the requests made for exactly publisher_name and returns number of rows deleted
+pub fn delete_request(conn: &DbConnection, publisher_name: String) -> Result<usize, Error> {
+    use schema::requests::dsl::*;
+
+    let publisher_name = DomainName::new(&publisher_name)?;
+    Ok(diesel::delete(requests.filter(publisher.eq(publisher_name.as_str()))).execute(conn)?)
 }
 
-//delete a request with publisher_name and returns number of rows deleted
-pub fn delete_request(conn: &MysqlConnection, publisher_name: String) -> Result<usize, String> {
+//returns the requests created from from up to but not including to, for publisher_name alone when one is given, oldest first
+pub fn requests_between(conn: &DbConnection, from: NaiveDateTime, to: NaiveDateTime, publisher_name: Option<&DomainName>, page: Page) -> Result<Vec<Request>, Error> {
     use schema::requests::dsl::*;
 
-    if improper_domain_name(&publisher_name) {
-        return Err(format!("Improper domain name {}", publisher_name));
+    let mut query = requests
+        .filter(created_at.ge(from))
+        .filter(created_at.lt(to))
+        .into_boxed();
+    if let Some(publisher_name) = publisher_name {
+        query = query.filter(publisher.eq(publisher_name.as_str()));
     }
+    Ok(query
+        .order((created_at.asc(), id.asc()))
+        .offset(page.offset)
+        .limit(page.limit)
+        .load::<Request>(conn)?)
+}
 
-    match diesel::delete(requests.filter(publisher.like(publisher_name))).execute(conn) {
-        Ok(val) => return Ok(val),
-        Err(_) => return Err(format!("Delete for Request has failed.")),
+//returns the number of requests requests_between finds over every page
+pub fn count_requests_between(conn: &DbConnection, from: NaiveDateTime, to: NaiveDateTime, publisher_name: Option<&DomainName>) -> Result<i64, Error> {
+    use schema::requests::dsl::*;
+
+    let mut query = requests
+        .filter(created_at.ge(from))
+        .filter(created_at.lt(to))
+        .into_boxed();
+    if let Some(publisher_name) = publisher_name {
+        query = query.filter(publisher.eq(publisher_name.as_str()));
     }
+    Ok(query.count().get_result(conn)?)
 }
 
+//deletes every request except the keep most recent ones with their responses and returns the number of requests and of responses deleted
+pub fn prune_requests(conn: &DbConnection, keep: i64) -> Result<(usize, usize), Error> {
+    use schema::{requests, responses};
 
-//returns the id number
-pub fn create_response<'a>(conn: &MysqlConnection, publisher_name: &'a str) -> Result<i32, String> {
-    use schema::responses;
-    use schema::responses::dsl::*;
+    //sqlite reads a negative offset as none, which would delete every row
+    if keep < 0 {
+        return Err(Error::Config(format!("Rows to keep must not be negative, not {}", keep)));
+    }
+    conn.transaction::<_, Error, _>(|| {
+        let newest_pruned = requests::table
+            .select(requests::id)
+            .order(requests::id.desc())
+            .offset(keep)
+            .first::<i32>(conn)
+            .optional()?;
+        match newest_pruned {
+            //sqlite does not cascade, so the responses are deleted here on every backend and counted
+            Some(val) => {
+                let responses_deleted = diesel::delete(responses::table.filter(responses::request_id.le(val))).execute(conn)?;
+                let requests_deleted = diesel::delete(requests::table.filter(requests::id.le(val))).execute(conn)?;
+                Ok((requests_deleted, responses_deleted))
+            }
+            None => Ok((0, 0)),
+        }
+    })
+}
 
-    if improper_domain_name(publisher_name) {
-        return Err(format!("Improper domain name {}", publisher_name));
+
+//records the response of the ad server, which has to be a listing, to the request and returns the id number
+pub fn create_response(conn: &DbConnection, request: i32, ad_server_name: &DomainName) -> Result<i32, Error> {
+    create_response_with_details(conn, request, ad_server_name, &ResponseDetails::default())
+}
+
+//the publisher is the one the request was made for
+pub fn create_response_with_details(conn: &DbConnection, request: i32, ad_server_name: &DomainName, details: &ResponseDetails) -> Result<i32, Error> {
+    use schema::{requests, responses};
+
+    let publisher_name = match requests::table.find(request).select(requests::publisher).first::<String>(conn).optional()? {
+        Some(val) => val,
+        None => return Err(Error::NotFound(format!("Request {}", request))),
+    };
+    //ad_server has no foreign key, so it stays after the listing is removed and is checked here instead
+    if !is_whitelisted(conn, ad_server_name)? {
+        return Err(Error::NotFound(format!("Listing for {}", ad_server_name)));
     }
 
     let new_response = NewResponse {
-        publisher: publisher_name,
+        publisher: &publisher_name,
+        request_id: request,
+        ad_server: ad_server_name.as_str(),
+        seat: details.seat.as_deref(),
+        bid_price_micros: details.bid_price_micros,
+        bid_currency: details.bid_currency.as_deref(),
+        creative_id: details.creative_id.as_deref(),
+        deal_id: details.deal_id.as_deref(),
+        latency_ms: details.latency_ms,
+        created_at: Utc::now().naive_utc(),
     };
+    insert_returning_id!(responses::table, responses::id, &new_response, conn)
+}
 
-    match diesel::insert_into(responses::table)
-        .values(&new_response)
-        .execute(conn)
-    {
-            Ok(val) => val,
-            Err(_) => return Err(format!("Insert for Response has failed.")),
+//parses an OpenRTB 2.5 BidResponse the ad server answered a request with, latency_ms after sending it
+//the request is the latest one with the auction id the response answers, returns the id numbers of the responses recorded, one for every bid
+pub fn ingest_openrtb_response(conn: &DbConnection, ad_server_name: &DomainName, json: &str, latency_ms: i32) -> Result<Vec<i32>, Error> {
+    let bid_response = BidResponse::parse(json)?;
+    let request = match get_request_by_auction_id(conn, &bid_response.id)? {
+        Some(val) => val.id,
+        None => return Err(Error::NotFound(format!("Request for auction {}", bid_response.id))),
     };
-    let response_inserted = match responses
-        .filter(publisher.like(publisher_name))
-        .limit(1)
-        .load::<Response>(conn)
-        {
-            Ok(val) => val,
-            Err(_) => return Err(format!("Could not retreive id for newly added Response.")),
-        };
-    Ok(response_inserted[0].id)
+    conn.transaction(|| {
+        let mut ids = Vec::new();
+        for details in bid_response.details(latency_ms) {
+            ids.push(create_response_with_details(conn, request, ad_server_name, &details)?);
+        }
+        Ok(ids)
+    })
+}
+
+//records that the bid of the response won at the clearing price, in millionths of the currency unit
+pub fn record_win(conn: &DbConnection, response: i32, clearing_price: i64) -> Result<(), Error> {
+    use schema::responses::dsl::*;
+
+    let updated = diesel::update(responses.find(response))
+        .set((won.eq(Some(true)), clearing_price_micros.eq(Some(clearing_price)), loss_reason.eq(None::<i32>)))
+        .execute(conn)?;
+    match updated {
+        0 => Err(Error::NotFound(format!("Response {}", response))),
+        _ => Ok(()),
+    }
 }
 
-//return the id of the last response made or return 0
-pub fn get_latest_response_id(conn: &MysqlConnection) -> Result<i32, String> {
+//records that the bid of the response lost, with the OpenRTB loss reason code when the exchange sends one
+pub fn record_loss(conn: &DbConnection, response: i32, reason: Option<i32>) -> Result<(), Error> {
     use schema::responses::dsl::*;
 
-    let latest_response = match responses
+    let updated = diesel::update(responses.find(response))
+        .set((won.eq(Some(false)), clearing_price_micros.eq(None::<i64>), loss_reason.eq(reason)))
+        .execute(conn)?;
+    match updated {
+        0 => Err(Error::NotFound(format!("Response {}", response))),
+        _ => Ok(()),
+    }
+}
+
+//returns the response with the id or None if there is none
+pub fn get_response(conn: &DbConnection, response: i32) -> Result<Option<Response>, Error> {
+    use schema::responses::dsl::*;
+
+    Ok(responses
+        .find(response)
+        .first::<Response>(conn)
+        .optional()?)
+}
+
+//takes request id and ad server pairs and returns the id numbers in the order the responses were given
+pub fn create_responses(conn: &DbConnection, new_responses: &[(i32, DomainName)]) -> Result<Vec<i32>, Error> {
+    conn.transaction(|| {
+        let mut ids = Vec::with_capacity(new_responses.len());
+        for &(request, ref ad_server_name) in new_responses {
+            ids.push(create_response(conn, request, ad_server_name)?);
+        }
+        Ok(ids)
+    })
+}
+
+//returns the responses to the request in the order they were made
+pub fn responses_for_request(conn: &DbConnection, request: i32) -> Result<Vec<Response>, Error> {
+    use schema::responses::dsl::*;
+
+    Ok(responses
+        .filter(request_id.eq(request))
+        .order(id.asc())
+        .load::<Response>(conn)?)
+}
+
+//returns the requests created at or after since (UTC) that no response answers, oldest first
+pub fn unanswered_requests(conn: &DbConnection, since: NaiveDateTime) -> Result<Vec<Request>, Error> {
+    use schema::{requests, responses};
+
+    Ok(requests::table
+        .left_join(responses::table)
+        .filter(responses::id.is_null())
+        .filter(requests::created_at.ge(since))
+        .select(requests::all_columns)
+        .order(requests::id.asc())
+        .load::<Request>(conn)?)
+}
+
+//return the id of the last response made
+pub fn get_latest_response_id(conn: &DbConnection) -> Result<i32, Error> {
+    use schema::responses::dsl::*;
+
+    let latest_response = responses
         .order(id.desc())
-        .limit(1)
-        .load::<Response>(conn)
-        {
-            Ok(val) => val,
-            Err(_) => return Err(format!("Could not retrieve id for Response.")),
-        };
-    Ok(latest_response[0].id)
+        .first::<Response>(conn)
+        .optional()?;
+    match latest_response {
+        Some(val) => Ok(val.id),
+        None => Err(Error::NotFound(format!("Response"))),
+    }
+}
+
+//delete the responses made for exactly publisher_name and returns number of rows deleted
+pub fn delete_response(conn: &DbConnection, publisher_name: String) -> Result<usize, Error> {
+    use schema::responses::dsl::*;
+
+    let publisher_name = DomainName::new(&publisher_name)?;
+    Ok(diesel::delete(responses.filter(publisher.eq(publisher_name.as_str()))).execute(conn)?)
+}
+
+//returns the responses created from from up to but not including to, for publisher_name alone when one is given, oldest first
+pub fn responses_between(conn: &DbConnection, from: NaiveDateTime, to: NaiveDateTime, publisher_name: Option<&DomainName>, page: Page) -> Result<Vec<Response>, Error> {
+    use schema::responses::dsl::*;
+
+    let mut query = responses
+        .filter(created_at.ge(from))
+        .filter(created_at.lt(to))
+        .into_boxed();
+    if let Some(publisher_name) = publisher_name {
+        query = query.filter(publisher.eq(publisher_name.as_str()));
+    }
+    Ok(query
+        .order((created_at.asc(), id.asc()))
+        .offset(page.offset)
+        .limit(page.limit)
+        .load::<Response>(conn)?)
 }
 
-//delete a response with publisher_name and returns number of rows deleted
-pub fn delete_response(conn: &MysqlConnection, publisher_name: String) -> Result<usize, String> {
+//returns the number of responses responses_between finds over every page
+pub fn count_responses_between(conn: &DbConnection, from: NaiveDateTime, to: NaiveDateTime, publisher_name: Option<&DomainName>) -> Result<i64, Error> {
     use schema::responses::dsl::*;
 
-    if improper_domain_name(&publisher_name) {
-        return Err(format!("Improper domain name {}", publisher_name));
+    let mut query = responses
+        .filter(created_at.ge(from))
+        .filter(created_at.lt(to))
+        .into_boxed();
+    if let Some(publisher_name) = publisher_name {
+        query = query.filter(publisher.eq(publisher_name.as_str()));
     }
+    Ok(query.count().get_result(conn)?)
+}
+
+//deletes every response except the keep most recent ones and returns number of rows deleted
+pub fn prune_responses(conn: &DbConnection, keep: i64) -> Result<usize, Error> {
+    use schema::responses::dsl::*;
 
-    match diesel::delete(responses.filter(publisher.like(publisher_name))).execute(conn) {
-        Ok(val) => return Ok(val),
-        Err(_) => return Err(format!("Delete for Response has failed.")),
+    if keep < 0 {
+        return Err(Error::Config(format!("Rows to keep must not be negative, not {}", keep)));
+    }
+    let newest_pruned = responses
+        .select(id)
+        .order(id.desc())
+        .offset(keep)
+        .first::<i32>(conn)
+        .optional()?;
+    match newest_pruned {
+        Some(val) => Ok(diesel::delete(responses.filter(id.le(val))).execute(conn)?),
+        None => Ok(0),
     }
 }
 
-//TODO: error handling
-//listen to events and maintain database based on the events
-pub fn maintain_database() {
-    //constants to compare values against
-    const RPC_ENDPOINT: &str = "http://localhost:8545";
-    const REGISTRY_ADDR: &str = "8009a230dc908e71befafba36e09efef2513640d";//THIS CHANGES BASED ON NETWORK
-
-    //create web3 transport and communication
-    let (_eloop, http) = web3::transports::Http::new(RPC_ENDPOINT).expect("Web3 failed to create transport.");
-    let web3 = web3::Web3::new(http);
-    
-    //create filters for the past and streaming events
-    let eth_filter_stream_events = EthFilter::new(web3.transport());
-    let eth_filter_past_events = EthFilter::new(web3.transport());
-
-    //create filter object to put perameters on the filtered events
-    let filter = FilterBuilder::default()
-        .from_block(BlockNumber::Number(0))
-        .to_block(BlockNumber::Latest)
-        .address(vec![H160::from_str(REGISTRY_ADDR).expect("Registry address could not be turned to H160.")]).build();
-    let filter_past_events = filter.clone();
-    
-    //create the filters to be used
-    let filter_stream_events = eth_filter_stream_events.create_logs_filter(filter).wait().expect("Filter could not be created.");
-    let filter_past_events = eth_filter_past_events.create_logs_filter(filter_past_events).wait().expect("Filter could not be created.");
-
-    //application and listing HashMap to keep track of domain names
-    let applications = Mutex::new(HashMap::new());
-
-    //start to stream events but not yet act on them
-    let filter_stream = filter_stream_events.stream(Duration::from_secs(0))
-        .for_each(|log| {
-            let data_vector = log.data.0;
-            let domain_name_hash = (&data_vector[0..32]).to_hex();
-            let topics = log.topics[0];
-            log_handler(domain_name_hash, &applications, topics, data_vector);
-            Ok(())
-        })
-        .map_err(|_| {
-            println!("Error with log stream");
-        });
-    //get all the past events
-    let past_logs = match filter_past_events.logs().wait() {
-        Ok(val) => val,
-        Err(e) => panic!(format!("Could not retrieve past logs on the Registry: {:?}", e)),
+//returns the last processed block number and log index for the registry or None if it has never been synced
+pub fn get_sync_state(conn: &DbConnection, registry_address: &str) -> Result<Option<SyncState>, Error> {
+    use schema::sync_state::dsl::*;
+
+    Ok(sync_state
+        .filter(registry.eq(registry_address))
+        .first::<SyncState>(conn)
+        .optional()?)
+}
+
+//records the last processed block number and log index for the registry and returns number of rows written
+pub fn update_sync_state(conn: &DbConnection, registry_address: &str, block: i64, index: i64) -> Result<usize, Error> {
+    use schema::sync_state;
+
+    let new_sync_state = NewSyncState {
+        registry: registry_address,
+        block_number: block,
+        log_index: index,
+    };
+    Ok(upsert!(sync_state::table, sync_state::registry, &new_sync_state, conn)?)
+}
+
+//returns the listing hash to domain name map of applications seen on the registry but not yet removed
+pub fn load_pending_applications(conn: &DbConnection, registry_address: &str) -> Result<HashMap<String, String>, Error> {
+    use schema::pending_applications::dsl::*;
+
+    let pending = pending_applications
+        .filter(registry.eq(registry_address))
+        .load::<PendingApplication>(conn)?;
+    Ok(pending.into_iter().map(|application| (application.listing_hash, application.domain)).collect())
+}
+
+//returns number of pending applications saved
+pub fn save_pending_application(conn: &DbConnection, registry_address: &str, hash: &str, domain_name: &str) -> Result<usize, Error> {
+    use schema::pending_applications;
+
+    let new_application = NewPendingApplication {
+        registry: registry_address,
+        listing_hash: hash,
+        domain: domain_name,
     };
+    Ok(upsert!(pending_applications::table, (pending_applications::registry, pending_applications::listing_hash), &new_application, conn)?)
+}
+
+//returns number of pending applications deleted
+pub fn delete_pending_application(conn: &DbConnection, registry_address: &str, hash: &str) -> Result<usize, Error> {
+    use schema::pending_applications::dsl::*;
+
+    Ok(diesel::delete(pending_applications
+        .filter(registry.eq(registry_address))
+        .filter(listing_hash.eq(hash)))
+        .execute(conn)?)
+}
+
+//returns number of processed logs recorded
+pub fn record_processed_log(conn: &DbConnection, processed_log: &NewProcessedLog) -> Result<usize, Error> {
+    use schema::processed_logs;
+
+    Ok(upsert!(processed_logs::table, (processed_logs::registry, processed_logs::block_number, processed_logs::log_index), processed_log, conn)?)
+}
+
+//returns the processed log at the given position or None if it was never processed
+pub fn get_processed_log(conn: &DbConnection, registry_address: &str, block: i64, index: i64) -> Result<Option<ProcessedLog>, Error> {
+    use schema::processed_logs::dsl::*;
+
+    Ok(processed_logs
+        .filter(registry.eq(registry_address))
+        .filter(block_number.eq(block))
+        .filter(log_index.eq(index))
+        .first::<ProcessedLog>(conn)
+        .optional()?)
+}
+
+//returns the most recently processed logs for the registry, newest first
+pub fn latest_processed_logs(conn: &DbConnection, registry_address: &str, count: i64) -> Result<Vec<ProcessedLog>, Error> {
+    use schema::processed_logs::dsl::*;
+
+    Ok(processed_logs
+        .filter(registry.eq(registry_address))
+        .order((block_number.desc(), log_index.desc()))
+        .limit(count)
+        .load::<ProcessedLog>(conn)?)
+}
+
+//returns the logs processed at or after block, newest first
+pub fn processed_logs_from(conn: &DbConnection, registry_address: &str, block: i64) -> Result<Vec<ProcessedLog>, Error> {
+    use schema::processed_logs::dsl::*;
+
+    Ok(processed_logs
+        .filter(registry.eq(registry_address))
+        .filter(block_number.ge(block))
+        .order((block_number.desc(), log_index.desc()))
+        .load::<ProcessedLog>(conn)?)
+}
+
+//returns number of processed logs deleted at or after block
+pub fn delete_processed_logs_from(conn: &DbConnection, registry_address: &str, block: i64) -> Result<usize, Error> {
+    use schema::processed_logs::dsl::*;
+
+    Ok(diesel::delete(processed_logs
+        .filter(registry.eq(registry_address))
+        .filter(block_number.ge(block)))
+        .execute(conn)?)
+}
+
+//returns number of rejected applications recorded
+pub fn create_rejected_application(conn: &DbConnection, rejected_application: &NewRejectedApplication) -> Result<usize, Error> {
+    use schema::rejected_applications;
+
+    Ok(diesel::insert_into(rejected_applications::table)
+        .values(rejected_application)
+        .execute(conn)?)
+}
+
+//returns every application on the registry whose domain did not hash to its listing hash
+pub fn get_rejected_applications(conn: &DbConnection, registry_address: &str) -> Result<Vec<RejectedApplication>, Error> {
+    use schema::rejected_applications::dsl::*;
 
-    //past_logs iterator
-    for log in past_logs {
-        let data_vector = log.data.0;
-        let domain_name_hash = (&data_vector[0..32]).to_hex();
-        let topics = log.topics[0];
-        log_handler(domain_name_hash, &applications, topics, data_vector);
+    Ok(rejected_applications
+        .filter(registry.eq(registry_address))
+        .order(id.asc())
+        .load::<RejectedApplication>(conn)?)
+}
+
+//returns number of rejected applications deleted at or after block
+pub fn delete_rejected_applications_from(conn: &DbConnection, registry_address: &str, block: i64) -> Result<usize, Error> {
+    use schema::rejected_applications::dsl::*;
+
+    Ok(diesel::delete(rejected_applications
+        .filter(registry.eq(registry_address))
+        .filter(block_number.ge(block)))
+        .execute(conn)?)
+}
+
+//returns the lifecycle state of the listing or None if the registry never saw it
+pub fn get_registry_listing(conn: &DbConnection, hash: &str) -> Result<Option<RegistryListing>, Error> {
+    use schema::registry_listings::dsl::*;
+
+    Ok(registry_listings
+        .filter(listing_hash.eq(hash))
+        .first::<RegistryListing>(conn)
+        .optional()?)
+}
+
+//returns the lifecycle state of the listing for the domain or None if the registry never saw it
+//the registry hashes the domain as it was applied with, so a name differing only in case is found through the domain column
+pub fn get_registry_listing_by_domain(conn: &DbConnection, domain_name: &str) -> Result<Option<RegistryListing>, Error> {
+    use schema::registry_listings::dsl::*;
+
+    let canonical = DomainName::new(domain_name)?;
+    match get_registry_listing(conn, &domain_listing_hash(domain_name))? {
+        Some(val) => Ok(Some(val)),
+        None => Ok(registry_listings
+            .filter(domain.eq(canonical.as_str()))
+            .first::<RegistryListing>(conn)
+            .optional()?),
     }
-    //start to iterate through new events
-    filter_stream.wait().expect("Could not start stream of new logs.");
-}
-
-//uses the log data to determine what action to take (what event the log references)
-fn log_handler(domain_name_hash: String, applications: & Mutex<HashMap<String, String>>, topics: H256, data_vector: Vec<u8>) {
-    const APPLICATION_HASH: &str = "5cde15b9901ca13a7e2eb4fb919870d1bde9e8d93d9aa5e26945b42190067bdc";
-    const NEW_LISTING_WHITELISTED_HASH: &str = "a7dee6157e26f0945c6e2fa27b51c0811370eb1863f1e5285e8dea4291fdd3de";
-    const APPLICATION_REMOVED_HASH: &str = "2e5ec035f6eac8ff1cf7cdf36cfeca7c85413f9f67652dc2c13d20f337204a26";
-    const LISTING_REMOVED_HASH: &str = "d1ffb796b7108387b2f02adf47b4b81a1690cf2a190422c87a4f670780103e63";
-
-    //println!("Domain Name Hash: {}", domain_name_hash);
-        if topics == H256::from_str(APPLICATION_HASH).expect("Const String could not be converted to H256.") {
-            //println!("Application Event");
-            let mut domain_name = String::new();
-            for iterator in 0..data_vector.len() {
-                if data_vector[(data_vector.len() - 1 - iterator) as usize] > 122 {
-                    break;
-                }
-                else if data_vector[(data_vector.len() - 1 - iterator) as usize] > 44 {
-                    domain_name.push(data_vector[(data_vector.len() - 1 - iterator) as usize] as char);
-                }
-                else {
-                    break;
-                }
-            }
-            unsafe {
-                domain_name.as_mut_vec().reverse();
-            }
-            //println!("Application Domain Name: {}", domain_name);
-            (*applications.lock().expect("Lock could not be unwrapped.")).insert(domain_name_hash, domain_name);
-        }
-        else if topics == H256::from_str(NEW_LISTING_WHITELISTED_HASH).expect("Const String could not be converted to H256.") {
-            //println!("New Listing Whitelisted Event");
-            //println!("Listing Domain Name: {:?}", (*applications.lock().expect("Lock could not be unwrapped.")).get(&domain_name_hash));
-            let connection = establish_connection();
-            let _creation = create_listing(&connection, (applications.lock().expect("Lock could not be unwrapped.").get(&domain_name_hash))
-                .expect("Error retrieving information from HashMap."));
-            //println!("Should be 1 entry: {}", _creation);
-        }
-        else if topics == H256::from_str(LISTING_REMOVED_HASH).expect("Const String could not be converted to H256.") {
-            //println!("Listing Removed Event");
-            //println!("Listing Domain Name: {:?}", (*applications.lock().expect("Lock could not be unwrapped.")).get(&domain_name_hash));
-            let connection = establish_connection();
-            let _deletion = delete_listing(&connection, (applications.lock().expect("Lock could not be unwrapped.").get(&domain_name_hash))
-                .expect("Error retrieving information from HashMap.").to_string());
-            //println!("Should be 1 entry: {}", _deletion);
-            (*applications.lock().expect("Lock could not be unwrapped.")).remove(&domain_name_hash);
-        }
-        else if topics == H256::from_str(APPLICATION_REMOVED_HASH).expect("Const String could not be converted to H256.") {
-            //println!("Application Removed Event");
-            //println!("Listing Domain Name: {:?}", (*applications.lock().expect("Lock could not be unwrapped.")).get(&domain_name_hash));
-            (*applications.lock().expect("Lock could not be unwrapped.")).remove(&domain_name_hash);
-        }
-}
\ No newline at end of file
+}
+
+//returns every listing the registry has seen ordered by domain
+pub fn get_registry_listings(conn: &DbConnection) -> Result<Vec<RegistryListing>, Error> {
+    use schema::registry_listings::dsl::*;
+
+    Ok(registry_listings
+        .order(domain.asc())
+        .load::<RegistryListing>(conn)?)
+}
+
+//returns every listing currently in the status
+pub fn get_registry_listings_with_status(conn: &DbConnection, listing_status: ListingStatus) -> Result<Vec<RegistryListing>, Error> {
+    use schema::registry_listings::dsl::*;
+
+    Ok(registry_listings
+        .filter(status.eq(listing_status.as_str()))
+        .order(domain.asc())
+        .load::<RegistryListing>(conn)?)
+}
+
+//returns number of registry listings written
+pub fn save_registry_listing(conn: &DbConnection, registry_listing: &RegistryListing) -> Result<usize, Error> {
+    use schema::registry_listings;
+
+    Ok(upsert!(registry_listings::table, registry_listings::listing_hash, registry_listing, conn)?)
+}
+
+//returns number of registry listings deleted
+pub fn delete_registry_listing(conn: &DbConnection, hash: &str) -> Result<usize, Error> {
+    use schema::registry_listings::dsl::*;
+
+    Ok(diesel::delete(registry_listings.filter(listing_hash.eq(hash))).execute(conn)?)
+}
+
+//returns number of challenges created
+pub fn create_challenge(conn: &DbConnection, new_challenge: &NewChallenge) -> Result<usize, Error> {
+    use schema::challenges;
+
+    Ok(upsert!(challenges::table, (challenges::registry, challenges::challenge_id), new_challenge, conn)?)
+}
+
+//returns the challenge or None if it was never seen on the registry
+pub fn get_challenge(conn: &DbConnection, registry_address: &str, id: &str) -> Result<Option<Challenge>, Error> {
+    use schema::challenges::dsl::*;
+
+    Ok(challenges
+        .filter(registry.eq(registry_address))
+        .filter(challenge_id.eq(id))
+        .first::<Challenge>(conn)
+        .optional()?)
+}
+
+//records the outcome of the challenge and returns number of challenges updated
+pub fn resolve_challenge(conn: &DbConnection, registry_address: &str, id: &str, challenge_outcome: ChallengeOutcome, block: i64, transaction: &str) -> Result<usize, Error> {
+    use schema::challenges::dsl::*;
+
+    Ok(diesel::update(challenges.filter(registry.eq(registry_address)).filter(challenge_id.eq(id)))
+        .set((
+            outcome.eq(challenge_outcome.as_str()),
+            resolved_block_number.eq(Some(block)),
+            resolved_transaction_hash.eq(Some(transaction)),
+        ))
+        .execute(conn)?)
+}
+
+//returns every challenge made against the listing of the domain, oldest first
+pub fn challenges_for_domain(conn: &DbConnection, domain_name: &str) -> Result<Vec<Challenge>, Error> {
+    use schema::challenges::dsl::*;
+
+    let hash = match get_registry_listing_by_domain(conn, domain_name)? {
+        Some(val) => val.listing_hash,
+        None => domain_listing_hash(domain_name),
+    };
+    Ok(challenges
+        .filter(listing_hash.eq(hash))
+        .order(block_number.asc())
+        .load::<Challenge>(conn)?)
+}
+
+//returns every challenge that has not been resolved yet, oldest first
+pub fn open_challenges(conn: &DbConnection) -> Result<Vec<Challenge>, Error> {
+    use schema::challenges::dsl::*;
+
+    Ok(challenges
+        .filter(outcome.eq(ChallengeOutcome::Open.as_str()))
+        .order(block_number.asc())
+        .load::<Challenge>(conn)?)
+}
+
+//returns number of challenges on the registry deleted that were made at or after block
+pub fn delete_challenges_from(conn: &DbConnection, registry_address: &str, block: i64) -> Result<usize, Error> {
+    use schema::challenges::dsl::*;
+
+    Ok(diesel::delete(challenges.filter(registry.eq(registry_address)).filter(block_number.ge(block))).execute(conn)?)
+}
+
+//returns number of challenges on the registry reopened that were resolved at or after block
+pub fn reopen_challenges_from(conn: &DbConnection, registry_address: &str, block: i64) -> Result<usize, Error> {
+    use schema::challenges::dsl::*;
+
+    Ok(diesel::update(challenges.filter(registry.eq(registry_address)).filter(resolved_block_number.ge(block)))
+        .set((
+            outcome.eq(ChallengeOutcome::Open.as_str()),
+            resolved_block_number.eq(None::<i64>),
+            resolved_transaction_hash.eq(None::<String>),
+        ))
+        .execute(conn)?)
+}
+
+//returns number of reward claims recorded
+pub fn create_reward_claim(conn: &DbConnection, new_reward_claim: &NewRewardClaim) -> Result<usize, Error> {
+    use schema::reward_claims;
+
+    Ok(diesel::insert_into(reward_claims::table)
+        .values(new_reward_claim)
+        .execute(conn)?)
+}
+
+//returns every reward claimed by voters of the challenge
+pub fn reward_claims_for_challenge(conn: &DbConnection, registry_address: &str, challenge: &str) -> Result<Vec<RewardClaim>, Error> {
+    use schema::reward_claims::dsl::*;
+
+    Ok(reward_claims
+        .filter(registry.eq(registry_address))
+        .filter(challenge_id.eq(challenge))
+        .order((block_number.asc(), log_index.asc()))
+        .load::<RewardClaim>(conn)?)
+}
+
+//returns number of reward claims on the registry deleted at or after block
+pub fn delete_reward_claims_from(conn: &DbConnection, registry_address: &str, block: i64) -> Result<usize, Error> {
+    use schema::reward_claims::dsl::*;
+
+    Ok(diesel::delete(reward_claims.filter(registry.eq(registry_address)).filter(block_number.ge(block))).execute(conn)?)
+}
+
+//returns number of stake changes recorded
+pub fn create_stake_change(conn: &DbConnection, new_stake_change: &NewStakeChange) -> Result<usize, Error> {
+    use schema::stake_changes;
+
+    Ok(diesel::insert_into(stake_changes::table)
+        .values(new_stake_change)
+        .execute(conn)?)
+}
+
+//returns the current stake of the listing for the domain with its history on the registry or None if the registry never saw it
+pub fn get_listing_stake(conn: &DbConnection, registry_address: &str, domain_name: &str) -> Result<Option<ListingStake>, Error> {
+    use schema::stake_changes::dsl::*;
+
+    let registry_listing = match get_registry_listing_by_domain(conn, domain_name)? {
+        Some(val) => val,
+        None => return Ok(None),
+    };
+
+    let history = stake_changes
+        .filter(registry.eq(registry_address))
+        .filter(listing_hash.eq(&registry_listing.listing_hash))
+        .order((block_number.asc(), log_index.asc()))
+        .load::<StakeChange>(conn)?;
+    Ok(Some(ListingStake {
+        listing_hash: registry_listing.listing_hash,
+        balance: registry_listing.unstaked_deposit,
+        history: history,
+    }))
+}
+
+//returns number of stake changes on the registry deleted at or after block
+pub fn delete_stake_changes_from(conn: &DbConnection, registry_address: &str, block: i64) -> Result<usize, Error> {
+    use schema::stake_changes::dsl::*;
+
+    Ok(diesel::delete(stake_changes.filter(registry.eq(registry_address)).filter(block_number.ge(block))).execute(conn)?)
+}
+
+pub use sync::{maintain_database, backfill, verify_registry, RegistrySync, Chain};
+pub use retention::{apply_retention, RetentionPolicy, RetentionReport};
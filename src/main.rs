@@ -1,12 +1,425 @@
 extern crate acbidder_database;
-use acbidder_database::maintain_database;
-//use acbidder_database::establish_connection;
-//use acbidder_database::current_auto_increment_value;
+extern crate clap;
+#[macro_use]
+extern crate serde_json;
+
+use std::process;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+
+use acbidder_database::{maintain_database, backfill, verify_registry};
+use acbidder_database::{apply_retention, RetentionPolicy};
+use acbidder_database::{create_pool, get_connection, run_migrations, applied_migrations};
+use acbidder_database::{get_whitelisted_domains, create_listing, delete_listing, is_whitelisted_host};
+use acbidder_database::{get_latest_request_id, prune_requests, get_latest_response_id, prune_responses};
+use acbidder_database::ConnectionPool;
+use acbidder_database::Config;
+use acbidder_database::DomainName;
+use acbidder_database::Error;
+use acbidder_database::models::HostMatchReason;
+use acbidder_database::config::parse_setting;
+use acbidder_database::api::serve;
 
 fn main() {
-	
-    //let connection = establish_connection();
-    //current_auto_increment_value(&connection);
+	let domain_arg = || Arg::with_name("domain")
+		.value_name("DOMAIN")
+		.required(true);
+	let keep_arg = || Arg::with_name("keep")
+		.long("keep")
+		.value_name("ROWS")
+		.help("Most recent rows to keep")
+		.required(true)
+		.takes_value(true)
+		//a negative count would be read as keeping nothing, so it is refused before the database is opened
+		.validator(|value| parse_setting::<u64>("--keep", &value).map(|_| ()).map_err(|e| e.to_string()));
+
+	let matches = App::new("acbidder_database")
+		.about("Keeps the bidder database in step with the AdChain Registry")
+		.after_help("Runs the sync when no subcommand is given")
+		.arg(Arg::with_name("json")
+			.long("json")
+			.help("Print the result as JSON")
+			.global(true))
+		.arg(Arg::with_name("config")
+			.long("config")
+			.value_name("FILE")
+			.help("TOML file to read settings from")
+			.takes_value(true))
+		.arg(Arg::with_name("rpc-url")
+			.long("rpc-url")
+			.value_name("URL")
+			.help("Ethereum JSON-RPC endpoint")
+			.takes_value(true))
+		.arg(Arg::with_name("registry-address")
+			.long("registry-address")
+			.value_name("ADDRESS")
+			.help("Address of the Registry contract")
+			.takes_value(true))
+		.arg(Arg::with_name("start-block")
+			.long("start-block")
+			.value_name("BLOCK")
+			.help("First block to read when the registry has never been synced")
+			.takes_value(true))
+		.arg(Arg::with_name("confirmation-depth")
+			.long("confirmation-depth")
+			.value_name("BLOCKS")
+			.help("Blocks a log must be buried under before it is applied")
+			.takes_value(true))
+		.arg(Arg::with_name("poll-interval")
+			.long("poll-interval")
+			.value_name("SECONDS")
+			.help("Seconds between polls for new logs")
+			.takes_value(true))
+		.arg(Arg::with_name("database-url")
+			.long("database-url")
+			.value_name("URL")
+			.help("Database to keep in step with the registry")
+			.takes_value(true))
+		.arg(Arg::with_name("pool-size")
+			.long("pool-size")
+			.value_name("CONNECTIONS")
+			.help("Most database connections to keep open")
+			.takes_value(true))
+		.arg(Arg::with_name("pool-timeout")
+			.long("pool-timeout")
+			.value_name("SECONDS")
+			.help("Seconds to wait for a free database connection")
+			.takes_value(true))
+		.arg(Arg::with_name("pool-health-check")
+			.long("pool-health-check")
+			.value_name("BOOL")
+			.help("Check database connections before using them")
+			.takes_value(true))
+		.arg(Arg::with_name("retention-days")
+			.long("retention-days")
+			.value_name("DAYS")
+			.help("Age in days after which retention deletes requests and responses")
+			.takes_value(true))
+		.arg(Arg::with_name("retention-chunk-size")
+			.long("retention-chunk-size")
+			.value_name("ROWS")
+			.help("Most rows retention deletes in one transaction")
+			.takes_value(true))
+		.arg(Arg::with_name("archive-dir")
+			.long("archive-dir")
+			.value_name("DIRECTORY")
+			.help("Directory retention archives deleted rows to")
+			.takes_value(true))
+		.subcommand(SubCommand::with_name("sync")
+			.about("Follows the registry and applies its events as they are confirmed"))
+		.subcommand(SubCommand::with_name("serve")
+			.about("Serves the listings and the latest request and response ids as a JSON API over HTTP")
+			.arg(Arg::with_name("listen")
+				.long("listen")
+				.value_name("ADDRESS")
+				.help("Address and port to listen on")
+				.default_value("127.0.0.1:3000")
+				.takes_value(true)))
+		.subcommand(SubCommand::with_name("backfill")
+			.about("Applies the confirmed registry events between two blocks once and exits")
+			.arg(Arg::with_name("from")
+				.long("from")
+				.value_name("BLOCK")
+				.help("First block to read, defaults to the checkpoint")
+				.takes_value(true))
+			.arg(Arg::with_name("to")
+				.long("to")
+				.value_name("BLOCK")
+				.help("Last block to read, defaults to the newest block")
+				.takes_value(true)))
+		.subcommand(SubCommand::with_name("listings")
+			.about("Manages the whitelisted listings")
+			.setting(AppSettings::SubcommandRequiredElseHelp)
+			.subcommand(SubCommand::with_name("list")
+				.about("Lists every whitelisted domain"))
+			.subcommand(SubCommand::with_name("add")
+				.about("Whitelists a domain outside the registry")
+				.arg(domain_arg()))
+			.subcommand(SubCommand::with_name("remove")
+				.about("Removes a domain from the whitelist")
+				.arg(domain_arg()))
+			.subcommand(SubCommand::with_name("check")
+				.about("Tells whether a host is served under a whitelisted listing")
+				.arg(domain_arg())))
+		.subcommand(SubCommand::with_name("requests")
+			.about("Inspects and prunes the requests table")
+			.setting(AppSettings::SubcommandRequiredElseHelp)
+			.subcommand(SubCommand::with_name("latest")
+				.about("Prints the id of the last request"))
+			.subcommand(SubCommand::with_name("prune")
+				.about("Deletes all but the most recent requests")
+				.arg(keep_arg())))
+		.subcommand(SubCommand::with_name("responses")
+			.about("Inspects and prunes the responses table")
+			.setting(AppSettings::SubcommandRequiredElseHelp)
+			.subcommand(SubCommand::with_name("latest")
+				.about("Prints the id of the last response"))
+			.subcommand(SubCommand::with_name("prune")
+				.about("Deletes all but the most recent responses")
+				.arg(keep_arg())))
+		.subcommand(SubCommand::with_name("db")
+			.about("Manages the database schema")
+			.setting(AppSettings::SubcommandRequiredElseHelp)
+			.subcommand(SubCommand::with_name("migrate")
+				.about("Runs the migrations that have not been run yet"))
+			.subcommand(SubCommand::with_name("status")
+				.about("Lists the migrations that have been run")))
+		.subcommand(SubCommand::with_name("verify")
+			.about("Compares the listings in the database with the registry contract"))
+		.subcommand(SubCommand::with_name("retention")
+			.about("Deletes the requests and responses older than the retention age, archiving them first when an archive directory is set"))
+		.get_matches();
+
+	let json = matches.is_present("json");
+	let config = match load_config(&matches) {
+		Ok(val) => val,
+		Err(e) => fail(&e, json),
+	};
+	let pool = match create_pool(&config) {
+		Ok(val) => val,
+		Err(e) => fail(&e, json),
+	};
+
+	let report = match matches.subcommand() {
+		("serve", Some(serve_matches)) => {
+			let address = serve_matches.value_of("listen").unwrap_or("127.0.0.1:3000");
+			let server = match serve(&pool, config.registry_address(), address, config.pool_size as usize) {
+				Ok(val) => val,
+				Err(e) => fail(&e, json),
+			};
+			Report::new(format!("Serving the API on {}", server.address()), json!({ "listening": server.address().to_string() })).print(json);
+			server.wait();
+			return;
+		}
+		("backfill", Some(backfill_matches)) => backfill_command(&pool, &config, backfill_matches),
+		("listings", Some(listings_matches)) => listings_command(&pool, listings_matches),
+		("requests", Some(requests_matches)) => requests_command(&pool, requests_matches),
+		("responses", Some(responses_matches)) => responses_command(&pool, responses_matches),
+		("db", Some(db_matches)) => db_command(&pool, db_matches),
+		("verify", Some(_)) => verify_command(&pool, &config),
+		("retention", Some(_)) => retention_command(&pool, &config),
+		//sync is also what runs without a subcommand
+		_ => {
+			maintain_database(&pool, &config, None);
+			return;
+		}
+	};
+	match report {
+		Ok(val) => val.print(json),
+		Err(e) => fail(&e, json),
+	}
+}
+
+//outcome of a command, printed for people or as JSON with --json
+struct Report {
+	text: String,
+	json: Value,
+}
+
+impl Report {
+	fn new(text: String, json: Value) -> Report {
+		Report {
+			text: text,
+			json: json,
+		}
+	}
+
+	fn print(&self, json: bool) {
+		if json {
+			println!("{}", self.json);
+		} else {
+			println!("{}", self.text);
+		}
+	}
+}
+
+fn fail(e: &Error, json: bool) -> ! {
+	if json {
+		println!("{}", json!({ "error": e.to_string() }));
+	} else {
+		eprintln!("{}", e);
+	}
+	process::exit(1);
+}
+
+fn backfill_command(pool: &ConnectionPool, config: &Config, matches: &ArgMatches) -> Result<Report, Error> {
+	let from_block = match matches.value_of("from") {
+		Some(val) => Some(parse_setting("--from", val)?),
+		None => None,
+	};
+	let to_block = match matches.value_of("to") {
+		Some(val) => Some(parse_setting("--to", val)?),
+		None => None,
+	};
+	let applied = backfill(pool, config, from_block, to_block)?;
+	Ok(Report::new(format!("Applied {} registry logs", applied), json!({ "applied": applied })))
+}
+
+fn listings_command(pool: &ConnectionPool, matches: &ArgMatches) -> Result<Report, Error> {
+	let conn = get_connection(pool)?;
+	match matches.subcommand() {
+		("add", Some(add_matches)) => {
+			let domain_name = DomainName::new(add_matches.value_of("domain").unwrap_or(""))?;
+			create_listing(&conn, &domain_name)?;
+			Ok(Report::new(format!("Added {}", domain_name), json!({ "domain": domain_name.as_str(), "added": true })))
+		}
+		("remove", Some(remove_matches)) => {
+			let domain_name = DomainName::new(remove_matches.value_of("domain").unwrap_or(""))?;
+			let removed = delete_listing(&conn, domain_name.as_str().to_string())? > 0;
+			let text = if removed {
+				format!("Removed {}", domain_name)
+			} else {
+				format!("{} was not listed", domain_name)
+			};
+			Ok(Report::new(text, json!({ "domain": domain_name.as_str(), "removed": removed })))
+		}
+		("check", Some(check_matches)) => {
+			let host = check_matches.value_of("domain").unwrap_or("");
+			match is_whitelisted_host(&conn, host)? {
+				Some(host_match) => {
+					let reason = match host_match.reason {
+						HostMatchReason::Exact => "exact",
+						HostMatchReason::ParentDomain => "parent_domain",
+						HostMatchReason::RegistrableDomain => "registrable_domain",
+					};
+					Ok(Report::new(
+						format!("{} is whitelisted through {} ({})", host_match.host, host_match.listing.domain, reason.replace('_', " ")),
+						json!({ "host": host_match.host.as_str(), "whitelisted": true, "listing": host_match.listing.domain, "reason": reason })))
+				}
+				None => {
+					let host = DomainName::new(host)?;
+					Ok(Report::new(format!("{} is not whitelisted", host), json!({ "host": host.as_str(), "whitelisted": false })))
+				}
+			}
+		}
+		_ => {
+			let domains = get_whitelisted_domains(&conn)?;
+			Ok(Report::new(domains.join("\n"), json!({ "listings": domains })))
+		}
+	}
+}
+
+fn requests_command(pool: &ConnectionPool, matches: &ArgMatches) -> Result<Report, Error> {
+	let conn = get_connection(pool)?;
+	match matches.subcommand() {
+		("prune", Some(prune_matches)) => {
+			let keep = parse_setting("--keep", prune_matches.value_of("keep").unwrap_or(""))?;
+			let (deleted, responses_deleted) = prune_requests(&conn, keep)?;
+			Ok(Report::new(format!("Deleted {} requests and their {} responses", deleted, responses_deleted), json!({ "deleted": deleted, "responses_deleted": responses_deleted })))
+		}
+		_ => latest_report("request", get_latest_request_id(&conn)),
+	}
+}
+
+fn responses_command(pool: &ConnectionPool, matches: &ArgMatches) -> Result<Report, Error> {
+	let conn = get_connection(pool)?;
+	match matches.subcommand() {
+		("prune", Some(prune_matches)) => {
+			let keep = parse_setting("--keep", prune_matches.value_of("keep").unwrap_or(""))?;
+			let deleted = prune_responses(&conn, keep)?;
+			Ok(Report::new(format!("Deleted {} responses", deleted), json!({ "deleted": deleted })))
+		}
+		_ => latest_report("response", get_latest_response_id(&conn)),
+	}
+}
+
+//an empty table is reported as having no latest id rather than as an error
+fn latest_report(kind: &str, latest: Result<i32, Error>) -> Result<Report, Error> {
+	match latest {
+		Ok(id) => Ok(Report::new(format!("Latest {} id is {}", kind, id), json!({ "id": id }))),
+		Err(Error::NotFound(_)) => Ok(Report::new(format!("There are no {}s", kind), json!({ "id": null }))),
+		Err(e) => Err(e),
+	}
+}
+
+fn db_command(pool: &ConnectionPool, matches: &ArgMatches) -> Result<Report, Error> {
+	let conn = get_connection(pool)?;
+	match matches.subcommand() {
+		("migrate", Some(_)) => {
+			let before = applied_migrations(&conn)?;
+			run_migrations(&conn)?;
+			let ran: Vec<String> = applied_migrations(&conn)?.into_iter().filter(|version| !before.contains(version)).collect();
+			let text = if ran.is_empty() {
+				format!("No migrations to run")
+			} else {
+				format!("Ran migrations {}", ran.join(", "))
+			};
+			Ok(Report::new(text, json!({ "ran": ran })))
+		}
+		_ => {
+			let applied = applied_migrations(&conn)?;
+			let text = match applied.last() {
+				Some(latest) => format!("{} migrations run, the latest is {}", applied.len(), latest),
+				None => format!("No migrations run"),
+			};
+			Ok(Report::new(text, json!({ "applied": applied })))
+		}
+	}
+}
+
+fn verify_command(pool: &ConnectionPool, config: &Config) -> Result<Report, Error> {
+	let mismatches = verify_registry(pool, config)?;
+	let text = if mismatches.is_empty() {
+		format!("Database matches the registry")
+	} else {
+		mismatches.iter()
+			.map(|mismatch| format!("{} {}: database {}, chain {}", mismatch.domain, mismatch.field, mismatch.database, mismatch.chain))
+			.collect::<Vec<String>>()
+			.join("\n")
+	};
+	Ok(Report::new(text, json!({ "mismatches": mismatches })))
+}
+
+fn retention_command(pool: &ConnectionPool, config: &Config) -> Result<Report, Error> {
+	let conn = get_connection(pool)?;
+	let report = apply_retention(&conn, &RetentionPolicy::from_config(config)?)?;
+	let mut text = format!("Deleted {} requests and {} responses created before {}", report.requests_deleted, report.responses_deleted, report.cutoff);
+	for archive in &report.archives {
+		text.push_str(&format!("\nArchived to {}", archive.display()));
+	}
+	Ok(Report::new(text, json!(report)))
+}
+
+//settings from the config file and the environment, overridden by the flags given on the command line
+fn load_config(matches: &ArgMatches) -> Result<Config, Error> {
+	let mut config = Config::load(matches.value_of("config"))?;
 
-    maintain_database();
+	if let Some(val) = matches.value_of("rpc-url") {
+		config.rpc_url = val.to_string();
+	}
+	if let Some(val) = matches.value_of("registry-address") {
+		config.registry_address = val.to_string();
+	}
+	if let Some(val) = matches.value_of("start-block") {
+		config.start_block = parse_setting("--start-block", val)?;
+	}
+	if let Some(val) = matches.value_of("confirmation-depth") {
+		config.confirmation_depth = parse_setting("--confirmation-depth", val)?;
+	}
+	if let Some(val) = matches.value_of("poll-interval") {
+		config.poll_interval = parse_setting("--poll-interval", val)?;
+	}
+	if let Some(val) = matches.value_of("database-url") {
+		config.database_url = Some(val.to_string());
+	}
+	if let Some(val) = matches.value_of("pool-size") {
+		config.pool_size = parse_setting("--pool-size", val)?;
+	}
+	if let Some(val) = matches.value_of("pool-timeout") {
+		config.pool_timeout = parse_setting("--pool-timeout", val)?;
+	}
+	if let Some(val) = matches.value_of("pool-health-check") {
+		config.pool_health_check = parse_setting("--pool-health-check", val)?;
+	}
+	if let Some(val) = matches.value_of("retention-days") {
+		config.retention_days = parse_setting("--retention-days", val)?;
+	}
+	if let Some(val) = matches.value_of("retention-chunk-size") {
+		config.retention_chunk_size = parse_setting("--retention-chunk-size", val)?;
+	}
+	if let Some(val) = matches.value_of("archive-dir") {
+		config.archive_dir = Some(val.to_string());
+	}
+	Ok(config)
 }
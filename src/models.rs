@@ -1,8 +1,20 @@
 use super::schema::listings;
 use super::schema::requests;
 use super::schema::responses;
+use super::schema::sync_state;
+use super::schema::pending_applications;
+use super::schema::processed_logs;
+use super::schema::rejected_applications;
+use super::schema::registry_listings;
+use super::schema::challenges;
+use super::schema::reward_claims;
+use super::schema::stake_changes;
 use diesel::sql_types::BigInt;
 
+use chrono::NaiveDateTime;
+
+use domain::DomainName;
+
 //listings
 #[derive(Insertable)]
 #[table_name = "listings"]
@@ -10,7 +22,7 @@ pub struct NewAdServer<'a> {
     pub domain: &'a str,
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Serialize, Clone, Debug)]
 pub struct AdServer {
     pub id: i32,
     pub domain: String,
@@ -22,13 +34,61 @@ pub struct AdServer {
 pub struct NewRequest<'a> {
     pub publisher: &'a str,
     pub userquality: i32,
+    pub received_at: Option<NaiveDateTime>,
+    pub auction_id: Option<&'a str>,
+    pub impressions: Option<i32>,
+    pub ad_format: Option<&'a str>,
+    pub ad_size: Option<&'a str>,
+    pub bid_floor_micros: Option<i64>,
+    pub bid_floor_currency: Option<&'a str>,
+    pub page_url: Option<&'a str>,
+    pub device_type: Option<i32>,
+    pub user_id_hash: Option<&'a str>,
+    pub region: Option<&'a str>,
+    pub created_at: NaiveDateTime,
 }
 
-#[derive(Queryable)]
+//what is known about a bid request besides its publisher and user quality, requests written before these were recorded have none of them
+//the format, size and floor are those of the first impression and prices are in millionths of the currency unit
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestDetails {
+    //UTC
+    pub received_at: Option<NaiveDateTime>,
+    pub auction_id: Option<String>,
+    pub impressions: Option<i32>,
+    //banner, video, native or audio
+    pub ad_format: Option<String>,
+    //width x height such as 300x250
+    pub ad_size: Option<String>,
+    pub bid_floor_micros: Option<i64>,
+    pub bid_floor_currency: Option<String>,
+    pub page_url: Option<String>,
+    //OpenRTB device type such as 1 for mobile or 2 for a personal computer
+    pub device_type: Option<i32>,
+    //keccak256 of the user id so the id itself is never stored
+    pub user_id_hash: Option<String>,
+    //country and region such as USA-CA
+    pub region: Option<String>,
+}
+
+#[derive(Queryable, Serialize, Clone, Debug)]
 pub struct Request {
     pub id: i32,
     pub publisher: String,
     pub userquality: i32,
+    pub received_at: Option<NaiveDateTime>,
+    pub auction_id: Option<String>,
+    pub impressions: Option<i32>,
+    pub ad_format: Option<String>,
+    pub ad_size: Option<String>,
+    pub bid_floor_micros: Option<i64>,
+    pub bid_floor_currency: Option<String>,
+    pub page_url: Option<String>,
+    pub device_type: Option<i32>,
+    pub user_id_hash: Option<String>,
+    pub region: Option<String>,
+    //UTC time the row was written, unlike received_at it is set for every request
+    pub created_at: NaiveDateTime,
 }
 
 //responses
@@ -36,12 +96,410 @@ pub struct Request {
 #[table_name = "responses"]
 pub struct NewResponse<'a> {
     pub publisher: &'a str,
+    pub request_id: i32,
+    pub ad_server: &'a str,
+    pub seat: Option<&'a str>,
+    pub bid_price_micros: Option<i64>,
+    pub bid_currency: Option<&'a str>,
+    pub creative_id: Option<&'a str>,
+    pub deal_id: Option<&'a str>,
+    pub latency_ms: Option<i32>,
+    pub created_at: NaiveDateTime,
 }
 
-#[derive(Queryable)]
+//the bid a response carries, a response without a price is a no-bid
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResponseDetails {
+    //bidder seat the bid was made on behalf of
+    pub seat: Option<String>,
+    //in millionths of the currency unit
+    pub bid_price_micros: Option<i64>,
+    pub bid_currency: Option<String>,
+    pub creative_id: Option<String>,
+    pub deal_id: Option<String>,
+    //milliseconds between sending the request and receiving the response
+    pub latency_ms: Option<i32>,
+}
+
+//request_id and ad_server are empty for responses written before they were recorded, and ad_server once its listing is removed
+//won is empty until a win or loss notice is recorded
+#[derive(Queryable, Serialize, Clone, Debug)]
 pub struct Response {
     pub id: i32,
     pub publisher: String,
+    pub request_id: Option<i32>,
+    pub ad_server: Option<String>,
+    pub seat: Option<String>,
+    pub bid_price_micros: Option<i64>,
+    pub bid_currency: Option<String>,
+    pub creative_id: Option<String>,
+    pub deal_id: Option<String>,
+    pub latency_ms: Option<i32>,
+    pub won: Option<bool>,
+    pub clearing_price_micros: Option<i64>,
+    //OpenRTB loss reason code such as 102 for a bid below the floor
+    pub loss_reason: Option<i32>,
+    //UTC time the row was written
+    pub created_at: NaiveDateTime,
+}
+
+//a window of rows for queries that can return many, offset rows are skipped and at most limit are returned
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Page {
+    pub offset: i64,
+    pub limit: i64,
+}
+
+impl Page {
+    pub fn first(limit: i64) -> Page {
+        Page {
+            offset: 0,
+            limit: limit,
+        }
+    }
+
+    //the page that follows this one
+    pub fn next(&self) -> Page {
+        Page {
+            offset: self.offset + self.limit,
+            limit: self.limit,
+        }
+    }
+}
+
+//sync state
+#[derive(Insertable, AsChangeset)]
+#[table_name = "sync_state"]
+pub struct NewSyncState<'a> {
+    pub registry: &'a str,
+    pub block_number: i64,
+    pub log_index: i64,
+}
+
+#[derive(Queryable)]
+pub struct SyncState {
+    pub registry: String,
+    pub block_number: i64,
+    pub log_index: i64,
+}
+
+//pending applications
+#[derive(Insertable, AsChangeset)]
+#[table_name = "pending_applications"]
+pub struct NewPendingApplication<'a> {
+    pub registry: &'a str,
+    pub listing_hash: &'a str,
+    pub domain: &'a str,
+}
+
+#[derive(Queryable)]
+pub struct PendingApplication {
+    pub registry: String,
+    pub listing_hash: String,
+    pub domain: String,
+}
+
+//processed logs
+#[derive(Insertable, AsChangeset)]
+#[table_name = "processed_logs"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewProcessedLog<'a> {
+    pub registry: &'a str,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub block_hash: &'a str,
+    pub transaction_hash: &'a str,
+    pub listing_hash: &'a str,
+    pub action: &'a str,
+    pub domain: Option<&'a str>,
+    pub previous_listing: Option<&'a str>,
+}
+
+#[derive(Queryable)]
+pub struct ProcessedLog {
+    pub registry: String,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub block_hash: String,
+    pub transaction_hash: String,
+    pub listing_hash: String,
+    pub action: String,
+    pub domain: Option<String>,
+    pub previous_listing: Option<String>,
+}
+
+//rejected applications
+#[derive(Insertable)]
+#[table_name = "rejected_applications"]
+pub struct NewRejectedApplication<'a> {
+    pub registry: &'a str,
+    pub listing_hash: &'a str,
+    pub data: &'a str,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub transaction_hash: &'a str,
+}
+
+#[derive(Queryable)]
+pub struct RejectedApplication {
+    pub id: i32,
+    pub registry: String,
+    pub listing_hash: String,
+    pub data: String,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub transaction_hash: String,
+}
+
+//registry listings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListingStatus {
+    Applied,
+    Challenged,
+    Whitelisted,
+    Removed,
+    Rejected,
+}
+
+impl ListingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ListingStatus::Applied => "applied",
+            ListingStatus::Challenged => "challenged",
+            ListingStatus::Whitelisted => "whitelisted",
+            ListingStatus::Removed => "removed",
+            ListingStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<ListingStatus> {
+        match status {
+            "applied" => Some(ListingStatus::Applied),
+            "challenged" => Some(ListingStatus::Challenged),
+            "whitelisted" => Some(ListingStatus::Whitelisted),
+            "removed" => Some(ListingStatus::Removed),
+            "rejected" => Some(ListingStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+//how a host matched a whitelisted listing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HostMatchReason {
+    //the host itself is listed
+    Exact,
+    //a parent of the host below its registrable domain is listed
+    ParentDomain,
+    //the registrable domain of the host, the public suffix and the label in front of it, is listed
+    RegistrableDomain,
+}
+
+//whitelisted listing a host is served under
+#[derive(Clone, Debug)]
+pub struct HostMatch {
+    pub host: DomainName,
+    pub listing: RegistryListing,
+    pub reason: HostMatchReason,
+}
+
+//field of a listing that differs between the database and the registry contract
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ListingMismatch {
+    pub domain: String,
+    pub listing_hash: String,
+    //whitelisted, listed, owner or unstaked_deposit
+    pub field: String,
+    pub database: String,
+    pub chain: String,
+}
+
+//uint256 amounts are stored as decimal strings since they do not fit in a bigint
+#[derive(Queryable, Insertable, AsChangeset, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[table_name = "registry_listings"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct RegistryListing {
+    pub listing_hash: String,
+    pub domain: String,
+    pub status: String,
+    pub owner: Option<String>,
+    pub unstaked_deposit: String,
+    pub application_expiry: Option<i64>,
+    pub challenge_id: Option<String>,
+    pub block_number: Option<i64>,
+    pub transaction_hash: Option<String>,
+}
+
+impl RegistryListing {
+    //a listing that has only been seen through its hash so far
+    pub fn new(listing_hash: &str, domain: &str, status: ListingStatus) -> RegistryListing {
+        RegistryListing {
+            listing_hash: listing_hash.to_string(),
+            domain: domain.to_string(),
+            status: status.as_str().to_string(),
+            owner: None,
+            unstaked_deposit: format!("0"),
+            application_expiry: None,
+            challenge_id: None,
+            block_number: None,
+            transaction_hash: None,
+        }
+    }
+
+    pub fn status(&self) -> Option<ListingStatus> {
+        ListingStatus::parse(&self.status)
+    }
+}
+
+//challenges
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChallengeOutcome {
+    Open,
+    Failed,
+    Succeeded,
+}
+
+impl ChallengeOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ChallengeOutcome::Open => "open",
+            ChallengeOutcome::Failed => "failed",
+            ChallengeOutcome::Succeeded => "succeeded",
+        }
+    }
+
+    pub fn parse(outcome: &str) -> Option<ChallengeOutcome> {
+        match outcome {
+            "open" => Some(ChallengeOutcome::Open),
+            "failed" => Some(ChallengeOutcome::Failed),
+            "succeeded" => Some(ChallengeOutcome::Succeeded),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "challenges"]
+pub struct NewChallenge<'a> {
+    pub registry: &'a str,
+    pub challenge_id: &'a str,
+    pub listing_hash: &'a str,
+    pub deposit: &'a str,
+    pub poll_id: &'a str,
+    pub data: &'a str,
+    pub outcome: &'a str,
+    pub block_number: i64,
+    pub transaction_hash: &'a str,
+}
+
+#[derive(Queryable, Serialize, Clone, Debug)]
+pub struct Challenge {
+    pub registry: String,
+    pub challenge_id: String,
+    pub listing_hash: String,
+    pub deposit: String,
+    pub poll_id: String,
+    pub data: String,
+    pub outcome: String,
+    pub block_number: i64,
+    pub transaction_hash: String,
+    pub resolved_block_number: Option<i64>,
+    pub resolved_transaction_hash: Option<String>,
+}
+
+impl Challenge {
+    pub fn outcome(&self) -> Option<ChallengeOutcome> {
+        ChallengeOutcome::parse(&self.outcome)
+    }
+}
+
+//reward claims
+#[derive(Insertable)]
+#[table_name = "reward_claims"]
+pub struct NewRewardClaim<'a> {
+    pub registry: &'a str,
+    pub challenge_id: &'a str,
+    pub voter: &'a str,
+    pub reward: &'a str,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub transaction_hash: &'a str,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct RewardClaim {
+    pub id: i32,
+    pub registry: String,
+    pub challenge_id: String,
+    pub voter: String,
+    pub reward: String,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub transaction_hash: String,
+}
+
+//stake changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StakeChangeKind {
+    Deposit,
+    Withdrawal,
+}
+
+impl StakeChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            StakeChangeKind::Deposit => "deposit",
+            StakeChangeKind::Withdrawal => "withdrawal",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<StakeChangeKind> {
+        match kind {
+            "deposit" => Some(StakeChangeKind::Deposit),
+            "withdrawal" => Some(StakeChangeKind::Withdrawal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "stake_changes"]
+pub struct NewStakeChange<'a> {
+    pub registry: &'a str,
+    pub listing_hash: &'a str,
+    pub kind: &'a str,
+    pub amount: &'a str,
+    pub new_total: &'a str,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub transaction_hash: &'a str,
+}
+
+#[derive(Queryable, Serialize, Clone, Debug)]
+pub struct StakeChange {
+    pub id: i32,
+    pub registry: String,
+    pub listing_hash: String,
+    pub kind: String,
+    pub amount: String,
+    pub new_total: String,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub transaction_hash: String,
+}
+
+impl StakeChange {
+    pub fn kind(&self) -> Option<StakeChangeKind> {
+        StakeChangeKind::parse(&self.kind)
+    }
+}
+
+//current unstaked deposit of a listing and the deposits and withdrawals that led to it, oldest first
+#[derive(Serialize, Clone, Debug)]
+pub struct ListingStake {
+    pub listing_hash: String,
+    pub balance: String,
+    pub history: Vec<StakeChange>,
 }
 
 #[allow(non_snake_case)]
@@ -49,4 +507,10 @@ pub struct Response {
 pub struct AutoIncrement {
 	#[sql_type = "BigInt"]
 	pub AUTO_INCREMENT: i64,
-}
\ No newline at end of file
+}
+//id given to the last row inserted on the connection
+#[derive(QueryableByName, Debug)]
+pub struct LastInsertId {
+	#[sql_type = "BigInt"]
+	pub id: i64,
+}
@@ -0,0 +1,308 @@
+use chrono::NaiveDateTime;
+use rustc_hex::ToHex;
+use serde_json;
+use serde_json::Value;
+use tiny_keccak::keccak256;
+
+use domain::DomainName;
+use error::Error;
+use models::{RequestDetails, ResponseDetails};
+
+//the parts of an OpenRTB 2.5 BidRequest that are recorded, every other field is ignored
+#[derive(Deserialize, Debug)]
+pub struct BidRequest {
+    pub id: String,
+    #[serde(default)]
+    pub imp: Vec<Imp>,
+    pub site: Option<Site>,
+    pub app: Option<App>,
+    pub device: Option<Device>,
+    pub user: Option<User>,
+    pub ext: Option<Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Imp {
+    pub banner: Option<Banner>,
+    pub video: Option<Video>,
+    pub audio: Option<Value>,
+    pub native: Option<Value>,
+    #[serde(default)]
+    pub bidfloor: f64,
+    pub bidfloorcur: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Banner {
+    pub w: Option<i32>,
+    pub h: Option<i32>,
+    //sizes the banner may be shown in, used when w and h are not given
+    #[serde(default)]
+    pub format: Vec<Format>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Format {
+    pub w: Option<i32>,
+    pub h: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Video {
+    pub w: Option<i32>,
+    pub h: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Site {
+    pub domain: Option<String>,
+    pub page: Option<String>,
+    pub publisher: Option<Publisher>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct App {
+    pub domain: Option<String>,
+    pub publisher: Option<Publisher>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Publisher {
+    pub domain: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Device {
+    pub devicetype: Option<i32>,
+    pub geo: Option<Geo>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct User {
+    pub id: Option<String>,
+    pub geo: Option<Geo>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Geo {
+    pub country: Option<String>,
+    pub region: Option<String>,
+}
+
+//currency of prices that do not name one, as in the OpenRTB specification
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+//the longest strings the columns they are recorded in can hold
+const MAX_ID_LENGTH: usize = 255;
+const MAX_CURRENCY_LENGTH: usize = 3;
+const MAX_REGION_LENGTH: usize = 64;
+const MAX_SEAT_LENGTH: usize = 64;
+
+//varchar lengths count characters, not bytes
+fn check_length(field: &str, value: &str, max: usize) -> Result<(), Error> {
+    if value.chars().count() > max {
+        return Err(Error::Decode(format!("OpenRTB field {} is longer than {} characters", field, max)));
+    }
+    Ok(())
+}
+
+impl BidRequest {
+    pub fn parse(json: &str) -> Result<BidRequest, Error> {
+        let bid_request: BidRequest = match serde_json::from_str(json) {
+            Ok(val) => val,
+            Err(e) => return Err(Error::Decode(format!("OpenRTB bid request could not be parsed: {}", e))),
+        };
+        bid_request.check_lengths()?;
+        Ok(bid_request)
+    }
+
+    //ad format and size are built here and always fit, the rest is copied from the request
+    fn check_lengths(&self) -> Result<(), Error> {
+        check_length("id", &self.id, MAX_ID_LENGTH)?;
+        for imp in &self.imp {
+            if let Some(ref currency) = imp.bidfloorcur {
+                check_length("imp.bidfloorcur", currency, MAX_CURRENCY_LENGTH)?;
+            }
+        }
+        let device_geo = self.device.as_ref().and_then(|device| device.geo.as_ref());
+        let user_geo = self.user.as_ref().and_then(|user| user.geo.as_ref());
+        for geo in device_geo.into_iter().chain(user_geo) {
+            if let Some(region) = geo.region() {
+                check_length("geo", &region, MAX_REGION_LENGTH)?;
+            }
+        }
+        Ok(())
+    }
+
+    //domain of the site or app the ad would be shown on, or of its publisher when the site or app has none
+    pub fn publisher(&self) -> Result<DomainName, Error> {
+        let site = self.site.as_ref().and_then(|site| {
+            site.domain.as_ref().or_else(|| site.publisher.as_ref().and_then(|publisher| publisher.domain.as_ref()))
+        });
+        let app = self.app.as_ref().and_then(|app| {
+            app.domain.as_ref().or_else(|| app.publisher.as_ref().and_then(|publisher| publisher.domain.as_ref()))
+        });
+        match site.or(app) {
+            Some(val) => DomainName::new(val),
+            None => Err(Error::Decode(format!("OpenRTB bid request {} names no publisher domain", self.id))),
+        }
+    }
+
+    //user quality is not part of OpenRTB, exchanges that score users send it as ext.userquality
+    pub fn user_quality(&self) -> i32 {
+        self.ext.as_ref()
+            .and_then(|ext| ext.get("userquality"))
+            .and_then(|quality| quality.as_i64())
+            .unwrap_or(0) as i32
+    }
+
+    pub fn details(&self, received_at: NaiveDateTime) -> RequestDetails {
+        let first = self.imp.first();
+        let device = self.device.as_ref();
+        let user = self.user.as_ref();
+        //the device reports where it is, the user where they live, which is only used when the device does not say
+        let geo = device.and_then(|device| device.geo.as_ref()).or_else(|| user.and_then(|user| user.geo.as_ref()));
+
+        RequestDetails {
+            received_at: Some(received_at),
+            auction_id: Some(self.id.clone()),
+            impressions: Some(self.imp.len() as i32),
+            ad_format: first.and_then(|imp| imp.format()).map(|format| format.to_string()),
+            ad_size: first.and_then(|imp| imp.size()),
+            bid_floor_micros: first.map(|imp| to_micros(imp.bidfloor)),
+            bid_floor_currency: first.map(|imp| imp.bidfloorcur.clone().unwrap_or_else(|| DEFAULT_CURRENCY.to_string())),
+            page_url: self.site.as_ref().and_then(|site| site.page.clone()),
+            device_type: device.and_then(|device| device.devicetype),
+            user_id_hash: user.and_then(|user| user.id.as_ref()).map(|id| keccak256(id.as_bytes()).to_hex()),
+            region: geo.and_then(|geo| geo.region()),
+        }
+    }
+}
+
+impl Imp {
+    fn format(&self) -> Option<&'static str> {
+        if self.banner.is_some() {
+            Some("banner")
+        } else if self.video.is_some() {
+            Some("video")
+        } else if self.native.is_some() {
+            Some("native")
+        } else if self.audio.is_some() {
+            Some("audio")
+        } else {
+            None
+        }
+    }
+
+    fn size(&self) -> Option<String> {
+        let (w, h) = if let Some(ref banner) = self.banner {
+            match (banner.w, banner.h, banner.format.first()) {
+                (Some(w), Some(h), _) => (Some(w), Some(h)),
+                (_, _, Some(format)) => (format.w, format.h),
+                _ => (None, None),
+            }
+        } else if let Some(ref video) = self.video {
+            (video.w, video.h)
+        } else {
+            (None, None)
+        };
+        match (w, h) {
+            (Some(w), Some(h)) => Some(format!("{}x{}", w, h)),
+            _ => None,
+        }
+    }
+}
+
+impl Geo {
+    fn region(&self) -> Option<String> {
+        match (self.country.as_ref(), self.region.as_ref()) {
+            (Some(country), Some(region)) => Some(format!("{}-{}", country, region)),
+            (Some(country), None) => Some(country.clone()),
+            (None, Some(region)) => Some(region.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+//turns a price in currency units into millionths of a unit
+pub fn to_micros(price: f64) -> i64 {
+    (price * 1_000_000.0).round() as i64
+}
+
+//the parts of an OpenRTB 2.5 BidResponse that are recorded, every other field is ignored
+#[derive(Deserialize, Debug)]
+pub struct BidResponse {
+    //id of the bid request it answers
+    pub id: String,
+    #[serde(default)]
+    pub seatbid: Vec<SeatBid>,
+    pub cur: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SeatBid {
+    #[serde(default)]
+    pub bid: Vec<Bid>,
+    pub seat: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Bid {
+    pub price: f64,
+    pub crid: Option<String>,
+    pub dealid: Option<String>,
+}
+
+impl BidResponse {
+    pub fn parse(json: &str) -> Result<BidResponse, Error> {
+        let bid_response: BidResponse = match serde_json::from_str(json) {
+            Ok(val) => val,
+            Err(e) => return Err(Error::Decode(format!("OpenRTB bid response could not be parsed: {}", e))),
+        };
+        bid_response.check_lengths()?;
+        Ok(bid_response)
+    }
+
+    fn check_lengths(&self) -> Result<(), Error> {
+        if let Some(ref currency) = self.cur {
+            check_length("cur", currency, MAX_CURRENCY_LENGTH)?;
+        }
+        for seat_bid in &self.seatbid {
+            if let Some(ref seat) = seat_bid.seat {
+                check_length("seatbid.seat", seat, MAX_SEAT_LENGTH)?;
+            }
+            for bid in &seat_bid.bid {
+                if let Some(ref crid) = bid.crid {
+                    check_length("bid.crid", crid, MAX_ID_LENGTH)?;
+                }
+                if let Some(ref dealid) = bid.dealid {
+                    check_length("bid.dealid", dealid, MAX_ID_LENGTH)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //one response for every bid, or a single no-bid when there are none
+    pub fn details(&self, latency_ms: i32) -> Vec<ResponseDetails> {
+        let currency = self.cur.clone().unwrap_or_else(|| DEFAULT_CURRENCY.to_string());
+        let mut details: Vec<ResponseDetails> = self.seatbid.iter()
+            .flat_map(|seat_bid| seat_bid.bid.iter().map(move |bid| (seat_bid, bid)))
+            .map(|(seat_bid, bid)| ResponseDetails {
+                seat: seat_bid.seat.clone(),
+                bid_price_micros: Some(to_micros(bid.price)),
+                bid_currency: Some(currency.clone()),
+                creative_id: bid.crid.clone(),
+                deal_id: bid.dealid.clone(),
+                latency_ms: Some(latency_ms),
+            })
+            .collect();
+        if details.is_empty() {
+            details.push(ResponseDetails {
+                latency_ms: Some(latency_ms),
+                ..ResponseDetails::default()
+            });
+        }
+        details
+    }
+}
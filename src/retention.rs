@@ -0,0 +1,208 @@
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use serde_json;
+
+use config::Config;
+use error::Error;
+use models::{Request, Response};
+use super::DbConnection;
+
+//how old requests and responses may get before apply_retention deletes them
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_age: Duration,
+    //most rows deleted at once, each chunk is its own transaction so the tables are never locked for long
+    pub chunk_size: i64,
+    //directory the rows are written to as gzipped newline-delimited JSON before they are deleted, nothing is archived when empty
+    pub archive_dir: Option<PathBuf>,
+}
+
+//ten thousand years, longer ages would not fit a date
+const MAX_RETENTION_DAYS: u64 = 3_652_425;
+
+impl RetentionPolicy {
+    pub fn from_config(config: &Config) -> Result<RetentionPolicy, Error> {
+        if config.retention_days > MAX_RETENTION_DAYS {
+            return Err(Error::Config(format!("Retention days must be at most {}, not {}", MAX_RETENTION_DAYS, config.retention_days)));
+        }
+        Ok(RetentionPolicy {
+            max_age: Duration::days(config.retention_days as i64),
+            chunk_size: config.retention_chunk_size,
+            archive_dir: config.archive_dir.as_ref().map(PathBuf::from),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RetentionReport {
+    //UTC, rows created before it were deleted
+    pub cutoff: NaiveDateTime,
+    pub requests_deleted: usize,
+    pub responses_deleted: usize,
+    //archive files written, one for each table that had rows to delete
+    pub archives: Vec<PathBuf>,
+}
+
+//gzipped newline-delimited JSON file of deleted rows, created when the first row is written to it
+struct Archive {
+    path: PathBuf,
+    encoder: Option<GzEncoder<File>>,
+}
+
+impl Archive {
+    //named after the table and the time retention started, such as requests-20180423T101500.ndjson.gz
+    fn new(dir: &Path, table: &str, started: NaiveDateTime) -> Archive {
+        Archive {
+            path: dir.join(format!("{}-{}.ndjson.gz", table, started.format("%Y%m%dT%H%M%S"))),
+            encoder: None,
+        }
+    }
+
+    //writes the rows one JSON object to a line and syncs them to disk so they are archived before they are deleted
+    fn write<T: Serialize>(&mut self, rows: &[T]) -> Result<(), Error> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        if self.encoder.is_none() {
+            //an archive is never overwritten, not even one left by an earlier run that started in the same second
+            let file = OpenOptions::new().write(true).create_new(true).open(&self.path).map_err(|e| archive_error(&self.path, e))?;
+            self.encoder = Some(GzEncoder::new(file, Compression::default()));
+        }
+        let path = &self.path;
+        let encoder = self.encoder.as_mut().expect("archive file was just opened");
+        for row in rows {
+            let line = match serde_json::to_string(row) {
+                Ok(val) => val,
+                Err(e) => return Err(Error::Archive(format!("Row could not be written as JSON to {}: {}", path.display(), e))),
+            };
+            encoder.write_all(line.as_bytes()).and_then(|_| encoder.write_all(b"\n")).map_err(|e| archive_error(path, e))?;
+        }
+        encoder.flush().and_then(|_| encoder.get_ref().sync_data()).map_err(|e| archive_error(path, e))
+    }
+
+    //ends the gzip stream and returns the path, or None if there were no rows to archive
+    fn finish(self) -> Result<Option<PathBuf>, Error> {
+        let Archive { path, encoder } = self;
+        match encoder {
+            Some(encoder) => {
+                let file = encoder.finish().map_err(|e| archive_error(&path, e))?;
+                file.sync_all().map_err(|e| archive_error(&path, e))?;
+                Ok(Some(path))
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+fn archive_error(path: &Path, e: io::Error) -> Error {
+    Error::Archive(format!("Could not write {}: {}", path.display(), e))
+}
+
+//deletes the requests created more than max_age ago with their responses and the responses created more than max_age ago,
+//chunk_size rows at a time, archiving them first when the policy has an archive directory
+//a chunk is archived before its transaction commits, so rows of a chunk that fails to delete are archived again by the next run
+pub fn apply_retention(conn: &DbConnection, policy: &RetentionPolicy) -> Result<RetentionReport, Error> {
+    use schema::{requests, responses};
+
+    if policy.chunk_size < 1 {
+        return Err(Error::Config(format!("Retention chunk size must be at least 1, not {}", policy.chunk_size)));
+    }
+    let started = Utc::now().naive_utc();
+    let cutoff = match started.checked_sub_signed(policy.max_age) {
+        Some(val) => val,
+        None => return Err(Error::Config(format!("Retention age of {} days is too long", policy.max_age.num_days()))),
+    };
+    let (mut request_archive, mut response_archive) = match policy.archive_dir {
+        Some(ref dir) => {
+            fs::create_dir_all(dir).map_err(|e| archive_error(dir, e))?;
+            (Some(Archive::new(dir, "requests", started)), Some(Archive::new(dir, "responses", started)))
+        },
+        None => (None, None),
+    };
+    let mut report = RetentionReport {
+        cutoff: cutoff,
+        requests_deleted: 0,
+        responses_deleted: 0,
+        archives: Vec::new(),
+    };
+
+    //old responses go first, including those to newer requests or written before responses were linked to their request
+    loop {
+        let deleted = conn.transaction::<_, Error, _>(|| {
+            let chunk = responses::table
+                .filter(responses::created_at.lt(cutoff))
+                .order(responses::id.asc())
+                .limit(policy.chunk_size)
+                .load::<Response>(conn)?;
+            let ids: Vec<i32> = chunk.iter().map(|response| response.id).collect();
+            if ids.is_empty() {
+                return Ok(0);
+            }
+            if let Some(ref mut archive) = response_archive {
+                archive.write(&chunk)?;
+            }
+            Ok(diesel::delete(responses::table.filter(responses::id.eq_any(&ids))).execute(conn)?)
+        })?;
+        if deleted == 0 {
+            break;
+        }
+        report.responses_deleted += deleted;
+    }
+
+    //the newer responses to an old request go before it, sqlite does not cascade and on the other backends the cascade would skip the archive
+    //a chunk deletes either the responses to its requests or the requests once they have none, so no transaction deletes more than chunk_size rows
+    loop {
+        let deleted = conn.transaction::<_, Error, _>(|| {
+            let chunk = requests::table
+                .filter(requests::created_at.lt(cutoff))
+                .order(requests::id.asc())
+                .limit(policy.chunk_size)
+                .load::<Request>(conn)?;
+            let ids: Vec<i32> = chunk.iter().map(|request| request.id).collect();
+            if ids.is_empty() {
+                return Ok(None);
+            }
+            let answers = responses::table
+                .filter(responses::request_id.eq_any(&ids))
+                .order(responses::id.asc())
+                .limit(policy.chunk_size)
+                .load::<Response>(conn)?;
+            if !answers.is_empty() {
+                if let Some(ref mut archive) = response_archive {
+                    archive.write(&answers)?;
+                }
+                let answer_ids: Vec<i32> = answers.iter().map(|response| response.id).collect();
+                let responses_deleted = diesel::delete(responses::table.filter(responses::id.eq_any(&answer_ids))).execute(conn)?;
+                return Ok(Some((0, responses_deleted)));
+            }
+            if let Some(ref mut archive) = request_archive {
+                archive.write(&chunk)?;
+            }
+            let requests_deleted = diesel::delete(requests::table.filter(requests::id.eq_any(&ids))).execute(conn)?;
+            Ok(Some((requests_deleted, 0)))
+        })?;
+        match deleted {
+            Some((requests_deleted, responses_deleted)) => {
+                report.requests_deleted += requests_deleted;
+                report.responses_deleted += responses_deleted;
+            },
+            None => break,
+        }
+    }
+
+    for archive in request_archive.into_iter().chain(response_archive) {
+        if let Some(path) = archive.finish()? {
+            report.archives.push(path);
+        }
+    }
+    Ok(report)
+}
@@ -10,6 +10,18 @@ table! {
         id -> Integer,
         publisher -> Varchar,
         userquality -> Integer,
+        received_at -> Nullable<Timestamp>,
+        auction_id -> Nullable<Varchar>,
+        impressions -> Nullable<Integer>,
+        ad_format -> Nullable<Varchar>,
+        ad_size -> Nullable<Varchar>,
+        bid_floor_micros -> Nullable<Bigint>,
+        bid_floor_currency -> Nullable<Varchar>,
+        page_url -> Nullable<Text>,
+        device_type -> Nullable<Integer>,
+        user_id_hash -> Nullable<Varchar>,
+        region -> Nullable<Varchar>,
+        created_at -> Timestamp,
     }
 }
 
@@ -17,11 +29,132 @@ table! {
     responses (id) {
         id -> Integer,
         publisher -> Varchar,
+        request_id -> Nullable<Integer>,
+        ad_server -> Nullable<Varchar>,
+        seat -> Nullable<Varchar>,
+        bid_price_micros -> Nullable<Bigint>,
+        bid_currency -> Nullable<Varchar>,
+        creative_id -> Nullable<Varchar>,
+        deal_id -> Nullable<Varchar>,
+        latency_ms -> Nullable<Integer>,
+        won -> Nullable<Bool>,
+        clearing_price_micros -> Nullable<Bigint>,
+        loss_reason -> Nullable<Integer>,
+        created_at -> Timestamp,
     }
 }
 
+table! {
+    sync_state (registry) {
+        registry -> Varchar,
+        block_number -> Bigint,
+        log_index -> Bigint,
+    }
+}
+
+table! {
+    pending_applications (registry, listing_hash) {
+        registry -> Varchar,
+        listing_hash -> Varchar,
+        domain -> Varchar,
+    }
+}
+
+table! {
+    processed_logs (registry, block_number, log_index) {
+        registry -> Varchar,
+        block_number -> Bigint,
+        log_index -> Bigint,
+        block_hash -> Varchar,
+        transaction_hash -> Varchar,
+        listing_hash -> Varchar,
+        action -> Varchar,
+        domain -> Nullable<Varchar>,
+        previous_listing -> Nullable<Text>,
+    }
+}
+
+table! {
+    rejected_applications (id) {
+        id -> Integer,
+        registry -> Varchar,
+        listing_hash -> Varchar,
+        data -> Text,
+        block_number -> Bigint,
+        log_index -> Bigint,
+        transaction_hash -> Varchar,
+    }
+}
+
+table! {
+    registry_listings (listing_hash) {
+        listing_hash -> Varchar,
+        domain -> Varchar,
+        status -> Varchar,
+        owner -> Nullable<Varchar>,
+        unstaked_deposit -> Varchar,
+        application_expiry -> Nullable<Bigint>,
+        challenge_id -> Nullable<Varchar>,
+        block_number -> Nullable<Bigint>,
+        transaction_hash -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    challenges (registry, challenge_id) {
+        registry -> Varchar,
+        challenge_id -> Varchar,
+        listing_hash -> Varchar,
+        deposit -> Varchar,
+        poll_id -> Varchar,
+        data -> Text,
+        outcome -> Varchar,
+        block_number -> Bigint,
+        transaction_hash -> Varchar,
+        resolved_block_number -> Nullable<Bigint>,
+        resolved_transaction_hash -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    reward_claims (id) {
+        id -> Integer,
+        registry -> Varchar,
+        challenge_id -> Varchar,
+        voter -> Varchar,
+        reward -> Varchar,
+        block_number -> Bigint,
+        log_index -> Bigint,
+        transaction_hash -> Varchar,
+    }
+}
+
+table! {
+    stake_changes (id) {
+        id -> Integer,
+        registry -> Varchar,
+        listing_hash -> Varchar,
+        kind -> Varchar,
+        amount -> Varchar,
+        new_total -> Varchar,
+        block_number -> Bigint,
+        log_index -> Bigint,
+        transaction_hash -> Varchar,
+    }
+}
+
+joinable!(responses -> requests (request_id));
+
 allow_tables_to_appear_in_same_query!(
     listings,
     requests,
     responses,
+    sync_state,
+    pending_applications,
+    processed_logs,
+    rejected_applications,
+    registry_listings,
+    challenges,
+    reward_claims,
+    stake_changes,
 );
@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+
+use domain::DomainName;
+
+//public suffix list checked in alongside the crate, from https://publicsuffix.org/list/public_suffix_list.dat
+const PUBLIC_SUFFIX_LIST: &str = include_str!("../public_suffix_list.dat");
+
+lazy_static! {
+    static ref BUNDLED: PublicSuffixList = PublicSuffixList::parse(PUBLIC_SUFFIX_LIST);
+}
+
+//suffixes under which anyone can register a name, such as com, co.uk or github.io
+//both the ICANN and the private sections of the list are used so a listing for a hosting platform does not cover its customers
+pub struct PublicSuffixList {
+    rules: HashSet<String>,
+    //suffixes of *.suffix rules, every label directly under them is a public suffix too
+    wildcards: HashSet<String>,
+    //names from !name rules that are registrable despite a wildcard rule covering them
+    exceptions: HashSet<String>,
+}
+
+impl PublicSuffixList {
+    //the list bundled with the crate, parsed the first time it is used
+    pub fn bundled() -> &'static PublicSuffixList {
+        &BUNDLED
+    }
+
+    //parses the list format, rules are converted to punycode so they compare against DomainName
+    pub fn parse(list: &str) -> PublicSuffixList {
+        let mut suffixes = PublicSuffixList {
+            rules: HashSet::new(),
+            wildcards: HashSet::new(),
+            exceptions: HashSet::new(),
+        };
+        for line in list.lines() {
+            //a rule is the first word on a line that is not a comment
+            let rule = match line.split_whitespace().next() {
+                Some(val) if !val.starts_with("//") => val,
+                _ => continue,
+            };
+            let (set, name) = if let Some(exception) = rule.strip_prefix('!') {
+                (&mut suffixes.exceptions, exception)
+            } else if let Some(wildcard) = rule.strip_prefix("*.") {
+                (&mut suffixes.wildcards, wildcard)
+            } else {
+                (&mut suffixes.rules, rule)
+            };
+            if let Ok(name) = DomainName::new(name) {
+                set.insert(name.into_string());
+            }
+        }
+        suffixes
+    }
+
+    //number of labels at the end of the name that make up its public suffix
+    //names no rule matches are treated as having a single label suffix
+    fn suffix_labels(&self, labels: &[&str]) -> usize {
+        for start in 0..labels.len() {
+            let candidate = labels[start..].join(".");
+            if self.exceptions.contains(&candidate) {
+                return labels.len() - start - 1;
+            }
+            if self.rules.contains(&candidate) {
+                return labels.len() - start;
+            }
+            if start + 1 < labels.len() && self.wildcards.contains(&labels[start + 1..].join(".")) {
+                return labels.len() - start;
+            }
+        }
+        1
+    }
+
+    pub fn public_suffix(&self, name: &DomainName) -> DomainName {
+        let labels: Vec<&str> = name.as_str().split('.').collect();
+        let suffix = labels[labels.len() - self.suffix_labels(&labels)..].join(".");
+        DomainName::new(&suffix).expect("public suffix of a domain name is a domain name")
+    }
+
+    //the public suffix and the label in front of it, None if the name is itself a public suffix
+    pub fn registrable_domain(&self, name: &DomainName) -> Option<DomainName> {
+        let labels: Vec<&str> = name.as_str().split('.').collect();
+        let suffix_labels = self.suffix_labels(&labels);
+        if labels.len() <= suffix_labels {
+            return None;
+        }
+        let registrable = labels[labels.len() - suffix_labels - 1..].join(".");
+        Some(DomainName::new(&registrable).expect("registrable part of a domain name is a domain name"))
+    }
+}
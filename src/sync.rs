@@ -0,0 +1,866 @@
+use std::cmp;
+use std::mem;
+use std::thread;
+use std::str::FromStr;
+use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::sync::mpsc::Sender;
+
+use diesel::Connection;
+
+use web3::Web3;
+use web3::types::*;
+use web3::futures::Future;
+use web3::api::BaseFilter;
+use web3::api::EthFilter;
+use web3::api::Namespace;
+use web3::transports::Http;
+
+use rustc_hex::ToHex;
+
+use serde_json;
+
+use models::{NewProcessedLog, NewRejectedApplication, RegistryListing, ListingStatus};
+use models::{NewChallenge, NewRewardClaim, ChallengeOutcome};
+use models::{NewStakeChange, StakeChangeKind, ListingMismatch};
+use decoder::{RegistryDecoder, RegistryEvent, ListingState};
+use error::Error;
+use domain::DomainName;
+use cache::WhitelistChange;
+use config::Config;
+use super::{DbConnection, ConnectionPool, get_connection};
+use super::{create_registry_listing_domain, delete_registry_listing_domain, get_listings, is_whitelisted};
+use super::{get_sync_state, update_sync_state};
+use super::{load_pending_applications, save_pending_application, delete_pending_application};
+use super::{record_processed_log, get_processed_log, latest_processed_logs, processed_logs_from, delete_processed_logs_from};
+use super::{create_rejected_application, delete_rejected_applications_from};
+use super::{get_registry_listing, get_registry_listings, save_registry_listing, delete_registry_listing};
+use super::{create_challenge, get_challenge, resolve_challenge, delete_challenges_from, reopen_challenges_from};
+use super::{create_reward_claim, delete_reward_claims_from};
+use super::{create_stake_change, delete_stake_changes_from};
+
+//actions recorded against processed logs so they can be undone after a reorg
+const ACTION_APPLICATION: &str = "application";
+const ACTION_WHITELISTED: &str = "whitelisted";
+const ACTION_APPLICATION_REMOVED: &str = "application_removed";
+const ACTION_LISTING_REMOVED: &str = "listing_removed";
+const ACTION_REJECTED: &str = "rejected";
+const ACTION_CHALLENGE: &str = "challenge";
+const ACTION_CHALLENGE_RESOLVED: &str = "challenge_resolved";
+const ACTION_REWARD_CLAIMED: &str = "reward_claimed";
+const ACTION_DEPOSIT: &str = "deposit";
+const ACTION_WITHDRAWAL: &str = "withdrawal";
+const ACTION_NONE: &str = "none";
+
+//seconds to wait before the first reconnection attempt after the sync fails
+const INITIAL_RETRY_DELAY: u64 = 1;
+
+//longest wait in seconds between reconnection attempts
+const MAX_RETRY_DELAY: u64 = 64;
+
+//number of most recently processed logs compared against the canonical chain on each poll
+const REORG_CHECK_LOGS: i64 = 64;
+
+//listen to events and maintain database based on the events
+//reconnects to the RPC endpoint and the database with exponential backoff whenever the sync fails
+//every listing added to or removed from the listings table is also sent to changes, such as a WhitelistCache kept up to date
+pub fn maintain_database(pool: &ConnectionPool, config: &Config, changes: Option<Sender<WhitelistChange>>) {
+    let mut backoff = Backoff::new();
+    loop {
+        if let Err(e) = sync_registry(pool, config, changes.as_ref(), &mut backoff) {
+            println!("Registry sync failed, reconnecting in {} seconds: {}", backoff.delay.as_secs(), e);
+            backoff.wait();
+        }
+    }
+}
+
+//connects to the database and the RPC endpoint and applies registry events until something fails
+fn sync_registry(pool: &ConnectionPool, config: &Config, changes: Option<&Sender<WhitelistChange>>, backoff: &mut Backoff) -> Result<(), Error> {
+    let registry_address = config.registry_address();
+    let connection = get_connection(pool)?;
+    let mut registry_sync = RegistrySync::load(&connection, registry_address, config.confirmation_depth, config.start_block, changes)?;
+
+    //create web3 transport and communication
+    let (_eloop, http) = Http::new(&config.rpc_url)?;
+    let web3 = Web3::new(http);
+
+    //create the streaming filter before reading past events so nothing is missed in between
+    let mut filter_stream_events = install_filter(&web3, registry_address, registry_sync.from_block(), BlockNumber::Latest)?;
+
+    //get all the past events since the last checkpoint
+    for log in fetch_logs(&web3, registry_address, registry_sync.from_block(), BlockNumber::Latest)? {
+        registry_sync.observe(log)?;
+    }
+
+    //apply logs as they are confirmed and undo them if the chain reorganizes
+    loop {
+        registry_sync.check_for_reorg(&web3)?;
+        if let Some(block) = registry_sync.refetch_from.take() {
+            for log in fetch_logs(&web3, registry_address, block, BlockNumber::Latest)? {
+                registry_sync.observe(log)?;
+            }
+        }
+        registry_sync.commit_confirmed(&web3)?;
+
+        match filter_stream_events.poll().wait() {
+            Ok(Some(logs)) => for log in logs {
+                registry_sync.observe(log)?;
+            },
+            Ok(None) => {},
+            //the node forgets filters that go unpolled for too long so install a new one and catch up on the logs it missed
+            Err(e) => {
+                println!("Error with log stream, reinstalling the filter: {:?}", e);
+                filter_stream_events = install_filter(&web3, registry_address, registry_sync.from_block(), BlockNumber::Latest)?;
+                for log in fetch_logs(&web3, registry_address, registry_sync.from_block(), BlockNumber::Latest)? {
+                    registry_sync.observe(log)?;
+                }
+            }
+        }
+        backoff.reset();
+        thread::sleep(Duration::from_secs(config.poll_interval));
+    }
+}
+
+//applies the registry logs from from_block to to_block once instead of following the chain and returns the number applied
+//from_block defaults to the checkpoint and to_block to the newest block, logs not yet buried under confirmation_depth blocks are left for the sync
+pub fn backfill(pool: &ConnectionPool, config: &Config, from_block: Option<u64>, to_block: Option<u64>) -> Result<usize, Error> {
+    let registry_address = config.registry_address();
+    let connection = get_connection(pool)?;
+    let mut registry_sync = RegistrySync::load(&connection, registry_address, config.confirmation_depth, config.start_block, None)?;
+
+    let checkpoint = registry_sync.from_block();
+    let from_block = match from_block {
+        //starting after the checkpoint would leave the logs in between unapplied for good
+        Some(val) if registry_sync.last_processed.is_some() && val > checkpoint => {
+            return Err(Error::Config(format!("Backfill from block {} would skip the logs after the checkpoint at block {}", val, checkpoint)));
+        }
+        Some(val) => val,
+        None => checkpoint,
+    };
+    let to_block = match to_block {
+        Some(val) if val < from_block => {
+            return Err(Error::Config(format!("Backfill to block {} ends before block {}", val, from_block)));
+        }
+        Some(val) => BlockNumber::Number(val),
+        None => BlockNumber::Latest,
+    };
+
+    let (_eloop, http) = Http::new(&config.rpc_url)?;
+    let web3 = Web3::new(http);
+    for log in fetch_logs(&web3, registry_address, from_block, to_block)? {
+        registry_sync.observe(log)?;
+    }
+    registry_sync.commit_confirmed(&web3)
+}
+
+//compares every listing in the database with the registry contract and returns the fields that differ
+//the contract is read confirmation_depth blocks behind the head, which is as far as a running sync has applied
+pub fn verify_registry(pool: &ConnectionPool, config: &Config) -> Result<Vec<ListingMismatch>, Error> {
+    let registry_address = config.registry_address();
+    let connection = get_connection(pool)?;
+    let decoder = RegistryDecoder::new()?;
+
+    let (_eloop, http) = Http::new(&config.rpc_url)?;
+    let web3 = Web3::new(http);
+    let head = web3.head()?;
+    let block = head.saturating_sub(config.confirmation_depth);
+
+    let listed: HashSet<String> = get_listings(&connection)?.into_iter().map(|ad_server| ad_server.domain).collect();
+    let mut mismatches = Vec::new();
+    for listing in get_registry_listings(&connection)? {
+        let listing_hash = match H256::from_str(&listing.listing_hash) {
+            Ok(val) => val,
+            Err(_) => return Err(Error::Decode(format!("Listing hash {} could not be turned to H256.", listing.listing_hash))),
+        };
+        let chain_listing = read_listing(&web3, &decoder, registry_address, &listing_hash, block)?;
+        let mut differs = |field: &str, database: String, chain: String| {
+            if database != chain {
+                mismatches.push(ListingMismatch {
+                    domain: listing.domain.clone(),
+                    listing_hash: listing.listing_hash.clone(),
+                    field: field.to_string(),
+                    database: database,
+                    chain: chain,
+                });
+            }
+        };
+
+        let whitelisted = listing.status() == Some(ListingStatus::Whitelisted);
+        differs("whitelisted", whitelisted.to_string(), chain_listing.whitelisted.to_string());
+        differs("listed", listed.contains(&listing.domain).to_string(), chain_listing.whitelisted.to_string());
+        //the contract forgets removed listings, only listings it still holds have an owner and deposit to compare
+        if chain_listing.owner != H160::default() {
+            differs("owner", listing.owner.clone().unwrap_or_default(), chain_listing.owner.to_hex());
+            differs("unstaked_deposit", listing.unstaked_deposit.clone(), chain_listing.unstaked_deposit.to_string());
+        }
+    }
+    Ok(mismatches)
+}
+
+//delay before the next reconnection attempt, doubled after every failure in a row
+struct Backoff {
+    delay: Duration,
+}
+
+impl Backoff {
+    fn new() -> Backoff {
+        Backoff {
+            delay: Duration::from_secs(INITIAL_RETRY_DELAY),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.delay = Duration::from_secs(INITIAL_RETRY_DELAY);
+    }
+
+    fn wait(&mut self) {
+        thread::sleep(self.delay);
+        self.delay = cmp::min(self.delay * 2, Duration::from_secs(MAX_RETRY_DELAY));
+    }
+}
+
+//filter for every log emitted by the registry from from_block up to to_block
+fn registry_filter(registry_address: &str, from_block: u64, to_block: BlockNumber) -> Result<Filter, Error> {
+    let address = registry_h160(registry_address)?;
+    Ok(FilterBuilder::default()
+        .from_block(BlockNumber::Number(from_block))
+        .to_block(to_block)
+        .address(vec![address]).build())
+}
+
+fn registry_h160(registry_address: &str) -> Result<H160, Error> {
+    match H160::from_str(registry_address) {
+        Ok(val) => Ok(val),
+        Err(_) => Err(Error::Decode(format!("Registry address {} could not be turned to H160.", registry_address))),
+    }
+}
+
+//installs a filter on the node for every log emitted by the registry from from_block up to to_block
+fn install_filter<'a>(web3: &'a Web3<Http>, registry_address: &str, from_block: u64, to_block: BlockNumber) -> Result<BaseFilter<&'a Http, Log>, Error> {
+    let eth_filter = EthFilter::new(web3.transport());
+    Ok(eth_filter.create_logs_filter(registry_filter(registry_address, from_block, to_block)?).wait()?)
+}
+
+//returns every log emitted by the registry from from_block up to to_block
+fn fetch_logs(web3: &Web3<Http>, registry_address: &str, from_block: u64, to_block: BlockNumber) -> Result<Vec<Log>, Error> {
+    let filter_past_events = install_filter(web3, registry_address, from_block, to_block)?;
+    Ok(filter_past_events.logs().wait()?)
+}
+
+//the chain as the sync reads it besides the logs, which is the node and in tests a chain the test controls
+pub trait Chain {
+    //number of the newest block
+    fn head(&self) -> Result<u64, Error>;
+    //returns the hash of the block currently at that height on the canonical chain
+    fn block_hash(&self, block: u64) -> Result<Option<H256>, Error>;
+    //calls the registry contract with the data as of block and returns its output
+    fn call_registry(&self, registry_address: &str, data: Vec<u8>, block: u64) -> Result<Vec<u8>, Error>;
+}
+
+impl Chain for Web3<Http> {
+    fn head(&self) -> Result<u64, Error> {
+        Ok(self.eth().block_number().wait()?.low_u64())
+    }
+
+    fn block_hash(&self, block: u64) -> Result<Option<H256>, Error> {
+        let canonical = self.eth().block(BlockId::Number(BlockNumber::Number(block))).wait()?;
+        Ok(canonical.and_then(|canonical| canonical.hash))
+    }
+
+    fn call_registry(&self, registry_address: &str, data: Vec<u8>, block: u64) -> Result<Vec<u8>, Error> {
+        let request = CallRequest {
+            from: None,
+            to: registry_h160(registry_address)?,
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: Some(Bytes(data)),
+        };
+        Ok(self.eth().call(request, Some(BlockNumber::Number(block))).wait()?.0)
+    }
+}
+
+//reads the listing from the registry contract as of block
+fn read_listing<C: Chain>(chain: &C, decoder: &RegistryDecoder, registry_address: &str, listing_hash: &H256, block: u64) -> Result<ListingState, Error> {
+    let output = chain.call_registry(registry_address, decoder.encode_listings_call(listing_hash)?, block)?;
+    decoder.decode_listing(&output)
+}
+
+//block number and log index of a mined log, pending logs have no position yet
+fn log_position(log: &Log) -> Option<(i64, i64)> {
+    match (log.block_number, log.log_index) {
+        (Some(block), Some(index)) => Some((block.low_u64() as i64, index.low_u64() as i64)),
+        _ => None,
+    }
+}
+
+fn hash_to_hex(hash: &H256) -> String {
+    hash.to_hex()
+}
+
+//event sync state for a single registry
+pub struct RegistrySync<'a> {
+    conn: &'a DbConnection,
+    registry: &'a str,
+    depth: u64,
+    //first block to read when the registry has never been synced
+    start_block: u64,
+    //listing hash to domain name of applications not yet removed
+    applications: HashMap<String, String>,
+    //block number and log index of the last log applied to the database
+    last_processed: Option<(i64, i64)>,
+    //logs seen but not yet buried under enough blocks
+    unconfirmed: Vec<Log>,
+    //set after a rollback so the replacement logs are read again
+    refetch_from: Option<u64>,
+    decoder: RegistryDecoder,
+    //where changes to the listings table are sent
+    changes: Option<&'a Sender<WhitelistChange>>,
+}
+
+impl<'a> RegistrySync<'a> {
+    //restores the checkpoint and pending applications from the last run
+    pub fn load(conn: &'a DbConnection, registry: &'a str, depth: u64, start_block: u64, changes: Option<&'a Sender<WhitelistChange>>) -> Result<RegistrySync<'a>, Error> {
+        let last_processed = get_sync_state(conn, registry)?
+            .map(|state| (state.block_number, state.log_index));
+        let applications = load_pending_applications(conn, registry)?;
+        let decoder = RegistryDecoder::new()?;
+
+        Ok(RegistrySync {
+            conn: conn,
+            registry: registry,
+            depth: depth,
+            start_block: start_block,
+            applications: applications,
+            last_processed: last_processed,
+            unconfirmed: Vec::new(),
+            refetch_from: None,
+            decoder: decoder,
+            changes: changes,
+        })
+    }
+
+    //tells the listener that the domain was added to or removed from the listings table
+    fn notify(&self, action: &str, domain_name: &str) {
+        let domain_name = match DomainName::new(domain_name) {
+            Ok(val) => val,
+            Err(_) => return,
+        };
+        let change = match action {
+            ACTION_WHITELISTED => WhitelistChange::Added(domain_name),
+            //a domain still whitelisted under another listing hash stays in the cache
+            ACTION_LISTING_REMOVED if is_whitelisted(self.conn, &domain_name).unwrap_or(false) => return,
+            ACTION_LISTING_REMOVED => WhitelistChange::Removed(domain_name),
+            _ => return,
+        };
+        if let Some(changes) = self.changes {
+            //a listener that went away misses nothing it still cares about
+            let _ = changes.send(change);
+        }
+    }
+
+    //first block that may still hold unprocessed logs
+    pub fn from_block(&self) -> u64 {
+        match self.last_processed {
+            Some((block, _)) if block > 0 => block as u64,
+            _ => self.start_block,
+        }
+    }
+
+    fn already_processed(&self, position: (i64, i64)) -> bool {
+        match self.last_processed {
+            Some(checkpoint) => position <= checkpoint,
+            None => false,
+        }
+    }
+
+    //queues a new log until it is confirmed or undoes a log the node reports as removed
+    pub fn observe(&mut self, log: Log) -> Result<(), Error> {
+        let position = match log_position(&log) {
+            Some(val) => val,
+            None => return Ok(()),
+        };
+        let same_log = |other: &Log| log_position(other) == Some(position) && other.block_hash == log.block_hash;
+
+        if log.removed == Some(true) {
+            let unconfirmed_count = self.unconfirmed.len();
+            self.unconfirmed.retain(|other| !same_log(other));
+            if self.unconfirmed.len() != unconfirmed_count {
+                return Ok(());
+            }
+            let processed = get_processed_log(self.conn, self.registry, position.0, position.1)?;
+            if let Some(processed) = processed {
+                if log.block_hash.map(|hash| hash_to_hex(&hash)) == Some(processed.block_hash) {
+                    self.rollback_from(position.0)?;
+                }
+            }
+            return Ok(());
+        }
+
+        if self.already_processed(position) || self.unconfirmed.iter().any(same_log) {
+            return Ok(());
+        }
+        self.unconfirmed.push(log);
+        Ok(())
+    }
+
+    //applies, in order, every queued log that is deep enough and still on the canonical chain and returns the number applied
+    pub fn commit_confirmed<C: Chain>(&mut self, chain: &C) -> Result<usize, Error> {
+        let head = chain.head()?;
+
+        let mut applied = 0;
+        let mut pending = mem::take(&mut self.unconfirmed);
+        pending.sort_by_key(log_position);
+        let mut canonical_hashes = HashMap::new();
+        for log in pending {
+            let position = match log_position(&log) {
+                Some(val) => val,
+                None => continue,
+            };
+            if position.0 as u64 + self.depth > head {
+                self.unconfirmed.push(log);
+                continue;
+            }
+            let canonical_hash = match canonical_hashes.entry(position.0) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => match chain.block_hash(position.0 as u64) {
+                    Ok(val) => *entry.insert(val),
+                    Err(e) => {
+                        //keep the log queued, it is read again once the sync reconnects
+                        self.unconfirmed.push(log);
+                        return Err(e);
+                    }
+                },
+            };
+            //logs from blocks that were reorganized away are dropped, their replacements arrive on their own
+            if canonical_hash != log.block_hash || self.already_processed(position) {
+                continue;
+            }
+            self.apply(chain, log, position)?;
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    //reads the listing from the registry contract as of block, None if the node no longer has that state
+    fn chain_listing<C: Chain>(&self, chain: &C, listing_hash: &H256, block: i64) -> Option<ListingState> {
+        read_listing(chain, &self.decoder, self.registry, listing_hash, block as u64).ok()
+    }
+
+    //every write the log makes commits together with the checkpoint, so a log that fails part way is applied whole on retry
+    fn apply<C: Chain>(&mut self, chain: &C, log: Log, position: (i64, i64)) -> Result<(), Error> {
+        //a Registry log that cannot be decoded stops the sync before the checkpoint passes it, so it is retried instead of lost
+        let event = match self.decoder.decode(&log) {
+            Ok(val) => val,
+            Err(Error::Decode(message)) => return Err(Error::Decode(format!("Log at block {} index {} could not be decoded: {}", position.0, position.1, message))),
+            Err(e) => return Err(e),
+        };
+        let listing_hash = match event {
+            Some(ref event) => match event.listing_hash() {
+                Some(hash) => hash_to_hex(&hash),
+                None => challenged_listing_hash(self.conn, self.registry, event)?,
+            },
+            None => String::new(),
+        };
+        //the contract is read before the transaction so no lock is held while waiting on the node
+        let chain_listing = match (event.as_ref(), H256::from_str(&listing_hash)) {
+            //removals clear the listing on chain so only the event itself is used for them
+            (Some(&RegistryEvent::ApplicationRemoved(_)), _) | (Some(&RegistryEvent::ListingRemoved(_)), _) => None,
+            (Some(_), Ok(hash)) => self.chain_listing(chain, &hash, position.0),
+            _ => None,
+        };
+
+        //the pending applications are only replaced once the transaction commits
+        let mut applications = self.applications.clone();
+        let (action, domain_name) = self.conn.transaction::<_, Error, _>(|| {
+            self.write_log(&log, position, event.as_ref(), &listing_hash, chain_listing, &mut applications)
+        })?;
+        self.applications = applications;
+        self.last_processed = Some(position);
+        if let Some(ref name) = domain_name {
+            self.notify(action, name);
+        }
+        Ok(())
+    }
+
+    //records the effects of the log, the log itself and the checkpoint, returns the action taken and the domain it applied to
+    fn write_log(&self, log: &Log, position: (i64, i64), event: Option<&RegistryEvent>, listing_hash: &str, chain_listing: Option<ListingState>, applications: &mut HashMap<String, String>) -> Result<(&'static str, Option<String>), Error> {
+        let block_hash = log.block_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+        let transaction_hash = log.transaction_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+        let mut previous_listing = None;
+        let (action, domain_name) = match event {
+            Some(event) => {
+                //the listing is moved first so the state it had before the log is what a rollback restores
+                if H256::from_str(listing_hash).is_ok() {
+                    let previous = transition_listing(self.conn, listing_hash, event, chain_listing, position.0, &transaction_hash)?;
+                    previous_listing = match serde_json::to_string(&previous) {
+                        Ok(val) => Some(val),
+                        Err(e) => return Err(Error::Decode(format!("RegistryListing could not be serialized: {}", e))),
+                    };
+                }
+                log_handler(self.conn, self.registry, listing_hash, applications, event, log, position)?
+            }
+            None => (ACTION_NONE, None),
+        };
+
+        let processed_log = NewProcessedLog {
+            registry: self.registry,
+            block_number: position.0,
+            log_index: position.1,
+            block_hash: &block_hash,
+            transaction_hash: &transaction_hash,
+            listing_hash: listing_hash,
+            action: action,
+            domain: domain_name.as_deref(),
+            previous_listing: previous_listing.as_deref(),
+        };
+        record_processed_log(self.conn, &processed_log)?;
+        update_sync_state(self.conn, self.registry, position.0, position.1)?;
+        Ok((action, domain_name))
+    }
+
+    //compares the blocks of the most recently processed logs against the canonical chain
+    pub fn check_for_reorg<C: Chain>(&mut self, chain: &C) -> Result<(), Error> {
+        let recent = latest_processed_logs(self.conn, self.registry, REORG_CHECK_LOGS)?;
+
+        let mut reorged_block = None;
+        let mut checked_block = None;
+        for processed in recent {
+            if checked_block == Some(processed.block_number) {
+                continue;
+            }
+            checked_block = Some(processed.block_number);
+            let canonical = chain.block_hash(processed.block_number as u64)?;
+            //every block before a canonical one is canonical as well
+            if canonical.map(|hash| hash_to_hex(&hash)) == Some(processed.block_hash) {
+                break;
+            }
+            reorged_block = Some(processed.block_number);
+        }
+
+        if let Some(block) = reorged_block {
+            println!("Chain reorganization detected at block {}", block);
+            self.rollback_from(block)?;
+        }
+        Ok(())
+    }
+
+    //undoes every log processed at or after block, newest first, and rewinds the checkpoint before it
+    //the whole rollback is one transaction so a failure part way leaves the logs applied and the checkpoint where it was
+    fn rollback_from(&mut self, block: i64) -> Result<(), Error> {
+        let mut applications = self.applications.clone();
+        let mut changes = Vec::new();
+        let checkpoint = self.conn.transaction::<_, Error, _>(|| {
+            self.write_rollback(block, &mut applications, &mut changes)
+        })?;
+        self.applications = applications;
+        for (action, domain_name) in changes {
+            self.notify(action, &domain_name);
+        }
+        self.last_processed = Some(checkpoint);
+        self.unconfirmed.retain(|log| log_position(log).is_some_and(|position| position.0 < block));
+        self.refetch_from = Some(block as u64);
+        Ok(())
+    }
+
+    //reverts the database for rollback_from and returns the new checkpoint, the listings added and removed are pushed to changes
+    fn write_rollback(&self, block: i64, applications: &mut HashMap<String, String>, changes: &mut Vec<(&'static str, String)>) -> Result<(i64, i64), Error> {
+        let reverted = processed_logs_from(self.conn, self.registry, block)?;
+        for processed in reverted {
+            let domain_name = processed.domain.unwrap_or_default();
+            match processed.action.as_str() {
+                ACTION_APPLICATION => {
+                    delete_pending_application(self.conn, self.registry, &processed.listing_hash)?;
+                    applications.remove(&processed.listing_hash);
+                }
+                ACTION_WHITELISTED => {
+                    let deleted = delete_registry_listing_domain(self.conn, domain_name.clone(), &processed.listing_hash)?;
+                    if deleted > 0 {
+                        changes.push((ACTION_LISTING_REMOVED, domain_name));
+                    }
+                }
+                ACTION_LISTING_REMOVED | ACTION_APPLICATION_REMOVED => {
+                    if processed.action == ACTION_LISTING_REMOVED {
+                        let listing_hash = &processed.listing_hash;
+                        match DomainName::new(&domain_name).and_then(|name| create_registry_listing_domain(self.conn, &name, listing_hash)) {
+                            Ok(_) => changes.push((ACTION_WHITELISTED, domain_name.clone())),
+                            Err(Error::DuplicateListing(_)) => {}
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    save_pending_application(self.conn, self.registry, &processed.listing_hash, &domain_name)?;
+                    applications.insert(processed.listing_hash.clone(), domain_name);
+                }
+                _ => {}
+            }
+
+            //restore the lifecycle state the listing had before the log
+            if let Some(previous_listing) = processed.previous_listing {
+                match serde_json::from_str::<Option<RegistryListing>>(&previous_listing) {
+                    Ok(Some(listing)) => {
+                        save_registry_listing(self.conn, &listing)?;
+                    }
+                    Ok(None) => {
+                        delete_registry_listing(self.conn, &processed.listing_hash)?;
+                    }
+                    Err(e) => println!("RegistryListing {} could not be restored: {}", processed.listing_hash, e),
+                }
+            }
+        }
+        delete_processed_logs_from(self.conn, self.registry, block)?;
+        delete_rejected_applications_from(self.conn, self.registry, block)?;
+        delete_challenges_from(self.conn, self.registry, block)?;
+        reopen_challenges_from(self.conn, self.registry, block)?;
+        delete_reward_claims_from(self.conn, self.registry, block)?;
+        delete_stake_changes_from(self.conn, self.registry, block)?;
+
+        //everything before the reorganized block is still canonical
+        let checkpoint = (block - 1, i64::MAX);
+        update_sync_state(self.conn, self.registry, checkpoint.0, checkpoint.1)?;
+        Ok(checkpoint)
+    }
+}
+
+//listing hash of the challenge a challenge outcome refers to or an empty string if the challenge is unknown
+fn challenged_listing_hash(conn: &DbConnection, registry_address: &str, event: &RegistryEvent) -> Result<String, Error> {
+    let challenge_id = match *event {
+        RegistryEvent::ChallengeFailed(ref challenge_failed) => challenge_failed.challenge_id,
+        RegistryEvent::ChallengeSucceeded(ref challenge_succeeded) => challenge_succeeded.challenge_id,
+        _ => return Ok(String::new()),
+    };
+    match get_challenge(conn, registry_address, &challenge_id.to_string())? {
+        Some(challenge) => Ok(challenge.listing_hash),
+        None => {
+            println!("Challenge {} resolved without being seen", challenge_id);
+            Ok(String::new())
+        }
+    }
+}
+
+//moves the registry listing through its lifecycle and returns the listing as it was before the event
+fn transition_listing(conn: &DbConnection, listing_hash: &str, event: &RegistryEvent, chain_listing: Option<ListingState>, block: i64, transaction_hash: &str) -> Result<Option<RegistryListing>, Error> {
+    let previous = get_registry_listing(conn, listing_hash)?;
+    let mut listing = match previous {
+        Some(ref val) => val.clone(),
+        None => RegistryListing::new(listing_hash, "", ListingStatus::Applied),
+    };
+
+    let status = match *event {
+        RegistryEvent::Application(ref application) => {
+            //rejected applications may not be valid domain names and keep the data they were applied with
+            listing.domain = match DomainName::new(&application.data) {
+                Ok(name) => name.into_string(),
+                Err(_) => application.data.clone(),
+            };
+            listing.unstaked_deposit = application.deposit.to_string();
+            listing.challenge_id = None;
+            if application.domain_matches_listing_hash() {
+                ListingStatus::Applied
+            } else {
+                ListingStatus::Rejected
+            }
+        }
+        RegistryEvent::Challenge(ref challenge) => {
+            //the challenge ID is the ID of the poll started for it
+            listing.challenge_id = Some(challenge.poll_id.to_string());
+            //a whitelisted listing stays whitelisted while the challenge against it is voted on
+            match listing.status() {
+                Some(ListingStatus::Whitelisted) => ListingStatus::Whitelisted,
+                _ => ListingStatus::Challenged,
+            }
+        }
+        //a failed challenge whitelists the listing, a successful one removes it with its own event
+        //only an application whose data hashed to its listing hash is whitelisted, a rejected or unknown listing keeps its status
+        RegistryEvent::ChallengeFailed(_) | RegistryEvent::NewListingWhitelisted(_) => match (previous.is_some(), listing.status()) {
+            (true, Some(ListingStatus::Applied)) | (true, Some(ListingStatus::Challenged)) | (true, Some(ListingStatus::Whitelisted)) => ListingStatus::Whitelisted,
+            (true, Some(status)) => status,
+            _ => return Ok(previous),
+        },
+        RegistryEvent::ChallengeSucceeded(_) => listing.status().unwrap_or(ListingStatus::Rejected),
+        RegistryEvent::Deposit(ref deposit) => {
+            listing.unstaked_deposit = deposit.new_total.to_string();
+            listing.status().unwrap_or(ListingStatus::Applied)
+        }
+        RegistryEvent::Withdrawal(ref withdrawal) => {
+            listing.unstaked_deposit = withdrawal.new_total.to_string();
+            listing.status().unwrap_or(ListingStatus::Applied)
+        }
+        //applications are only removed when a challenge against them succeeds
+        RegistryEvent::ApplicationRemoved(_) => ListingStatus::Rejected,
+        RegistryEvent::ListingRemoved(_) => ListingStatus::Removed,
+        _ => return Ok(previous),
+    };
+    listing.status = status.as_str().to_string();
+
+    //the contract holds the owner and expiry, which the events do not carry
+    if let Some(chain_listing) = chain_listing {
+        if chain_listing.owner != H160::default() {
+            listing.owner = Some(chain_listing.owner.to_hex());
+            listing.application_expiry = Some(chain_listing.application_expiry.low_u64() as i64);
+            listing.unstaked_deposit = chain_listing.unstaked_deposit.to_string();
+            if chain_listing.challenge_id != U256::from(0) {
+                listing.challenge_id = Some(chain_listing.challenge_id.to_string());
+            }
+        }
+    }
+    listing.block_number = Some(block);
+    listing.transaction_hash = Some(transaction_hash.to_string());
+
+    save_registry_listing(conn, &listing)?;
+    Ok(previous)
+}
+
+//uses the decoded event to determine what action to take
+//returns the action taken and the domain name it applied to so it can be undone
+fn log_handler(conn: &DbConnection, registry_address: &str, domain_name_hash: &str, applications: &mut HashMap<String, String>, event: &RegistryEvent, log: &Log, position: (i64, i64)) -> Result<(&'static str, Option<String>), Error> {
+    match *event {
+        RegistryEvent::Application(ref application) => {
+            //an application whose data does not hash to its listing hash must never be whitelisted
+            if !application.domain_matches_listing_hash() {
+                let raw_data: String = log.data.0.to_hex();
+                let transaction_hash = log.transaction_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+                let rejected_application = NewRejectedApplication {
+                    registry: registry_address,
+                    listing_hash: domain_name_hash,
+                    data: &raw_data,
+                    block_number: position.0,
+                    log_index: position.1,
+                    transaction_hash: &transaction_hash,
+                };
+                create_rejected_application(conn, &rejected_application)?;
+                println!("Application {} rejected: data does not hash to the listing hash", domain_name_hash);
+                return Ok((ACTION_REJECTED, None));
+            }
+            let domain_name = application.data.clone();
+            save_pending_application(conn, registry_address, domain_name_hash, &domain_name)?;
+            applications.insert(domain_name_hash.to_string(), domain_name.clone());
+            Ok((ACTION_APPLICATION, Some(domain_name)))
+        }
+        RegistryEvent::NewListingWhitelisted(_) => {
+            //rejected applications never make it into the map
+            let domain_name = match applications.get(domain_name_hash) {
+                Some(val) => val.clone(),
+                None => {
+                    println!("Listing {} whitelisted without a valid application", domain_name_hash);
+                    return Ok((ACTION_NONE, None));
+                }
+            };
+            match DomainName::new(&domain_name).and_then(|name| create_registry_listing_domain(conn, &name, domain_name_hash)) {
+                Ok(_) => Ok((ACTION_WHITELISTED, Some(domain_name))),
+                //a listing that already existed must not be deleted on rollback
+                Err(Error::DuplicateListing(_)) => Ok((ACTION_NONE, Some(domain_name))),
+                //retrying would never succeed so the listing is left out of the listings table
+                Err(Error::InvalidDomain(_)) => {
+                    println!("Listing {} whitelisted with an improper domain name", domain_name_hash);
+                    Ok((ACTION_NONE, Some(domain_name)))
+                }
+                Err(e) => Err(e),
+            }
+        }
+        RegistryEvent::ListingRemoved(_) => {
+            let domain_name = match applications.get(domain_name_hash) {
+                Some(val) => val.clone(),
+                None => {
+                    println!("Listing {} removed without a valid application", domain_name_hash);
+                    return Ok((ACTION_NONE, None));
+                }
+            };
+            //an improper domain name was never added to the listings table
+            //the listing stays while the domain is whitelisted by an application written differently
+            match delete_registry_listing_domain(conn, domain_name.clone(), domain_name_hash) {
+                Ok(_) | Err(Error::InvalidDomain(_)) => {}
+                Err(e) => return Err(e),
+            }
+            delete_pending_application(conn, registry_address, domain_name_hash)?;
+            applications.remove(domain_name_hash);
+            Ok((ACTION_LISTING_REMOVED, Some(domain_name)))
+        }
+        RegistryEvent::ApplicationRemoved(_) => {
+            delete_pending_application(conn, registry_address, domain_name_hash)?;
+            match applications.remove(domain_name_hash) {
+                Some(domain_name) => Ok((ACTION_APPLICATION_REMOVED, Some(domain_name))),
+                None => Ok((ACTION_NONE, None)),
+            }
+        }
+        RegistryEvent::Challenge(ref challenge) => {
+            let challenge_id = challenge.poll_id.to_string();
+            let deposit = challenge.deposit.to_string();
+            let transaction_hash = log.transaction_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+            let new_challenge = NewChallenge {
+                registry: registry_address,
+                challenge_id: &challenge_id,
+                listing_hash: domain_name_hash,
+                deposit: &deposit,
+                poll_id: &challenge_id,
+                data: &challenge.data,
+                outcome: ChallengeOutcome::Open.as_str(),
+                block_number: position.0,
+                transaction_hash: &transaction_hash,
+            };
+            create_challenge(conn, &new_challenge)?;
+            Ok((ACTION_CHALLENGE, applications.get(domain_name_hash).cloned()))
+        }
+        RegistryEvent::ChallengeFailed(ref challenge_failed) => {
+            let transaction_hash = log.transaction_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+            resolve_challenge(conn, registry_address, &challenge_failed.challenge_id.to_string(), ChallengeOutcome::Failed, position.0, &transaction_hash)?;
+            Ok((ACTION_CHALLENGE_RESOLVED, None))
+        }
+        RegistryEvent::ChallengeSucceeded(ref challenge_succeeded) => {
+            let transaction_hash = log.transaction_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+            resolve_challenge(conn, registry_address, &challenge_succeeded.challenge_id.to_string(), ChallengeOutcome::Succeeded, position.0, &transaction_hash)?;
+            Ok((ACTION_CHALLENGE_RESOLVED, None))
+        }
+        RegistryEvent::RewardClaimed(ref reward_claimed) => {
+            let challenge_id = reward_claimed.challenge_id.to_string();
+            let voter: String = reward_claimed.voter.to_hex();
+            let reward = reward_claimed.reward.to_string();
+            let transaction_hash = log.transaction_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+            let new_reward_claim = NewRewardClaim {
+                registry: registry_address,
+                challenge_id: &challenge_id,
+                voter: &voter,
+                reward: &reward,
+                block_number: position.0,
+                log_index: position.1,
+                transaction_hash: &transaction_hash,
+            };
+            create_reward_claim(conn, &new_reward_claim)?;
+            Ok((ACTION_REWARD_CLAIMED, None))
+        }
+        RegistryEvent::Deposit(ref deposit) => {
+            let amount = deposit.added.to_string();
+            let new_total = deposit.new_total.to_string();
+            let transaction_hash = log.transaction_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+            let new_stake_change = NewStakeChange {
+                registry: registry_address,
+                listing_hash: domain_name_hash,
+                kind: StakeChangeKind::Deposit.as_str(),
+                amount: &amount,
+                new_total: &new_total,
+                block_number: position.0,
+                log_index: position.1,
+                transaction_hash: &transaction_hash,
+            };
+            create_stake_change(conn, &new_stake_change)?;
+            Ok((ACTION_DEPOSIT, applications.get(domain_name_hash).cloned()))
+        }
+        RegistryEvent::Withdrawal(ref withdrawal) => {
+            let amount = withdrawal.withdrew.to_string();
+            let new_total = withdrawal.new_total.to_string();
+            let transaction_hash = log.transaction_hash.map(|hash| hash_to_hex(&hash)).unwrap_or_default();
+            let new_stake_change = NewStakeChange {
+                registry: registry_address,
+                listing_hash: domain_name_hash,
+                kind: StakeChangeKind::Withdrawal.as_str(),
+                amount: &amount,
+                new_total: &new_total,
+                block_number: position.0,
+                log_index: position.1,
+                transaction_hash: &transaction_hash,
+            };
+            create_stake_change(conn, &new_stake_change)?;
+            Ok((ACTION_WITHDRAWAL, applications.get(domain_name_hash).cloned()))
+        }
+    }
+}
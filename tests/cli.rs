@@ -0,0 +1,32 @@
+use std::process::Command;
+
+//a prune without --keep is rejected before the database is opened
+#[test]
+fn test_prune_without_keep_is_rejected() {
+    for table in &["requests", "responses"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_acbidder_database"))
+            .arg(table)
+            .arg("prune")
+            .env("DATABASE_URL", "unreachable://")
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{} prune without --keep succeeded", table);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--keep"), "{} prune did not ask for --keep", table);
+    }
+}
+
+//a negative --keep would delete every row on sqlite, so it is rejected as well
+#[test]
+fn test_prune_with_negative_keep_is_rejected() {
+    for table in &["requests", "responses"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_acbidder_database"))
+            .arg(table)
+            .arg("prune")
+            .arg("--keep=-1")
+            .env("DATABASE_URL", "unreachable://")
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{} prune with a negative --keep succeeded", table);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--keep could not be parsed from -1"), "{} prune did not refuse the negative --keep", table);
+    }
+}
@@ -0,0 +1,46 @@
+extern crate acbidder_database;
+
+use acbidder_database::Config;
+use acbidder_database::Error;
+use acbidder_database::RetentionPolicy;
+use acbidder_database::config::parse_setting;
+
+//settings left out of the file keep their defaults
+#[test]
+fn test_partial_config_file_keeps_defaults() {
+    let config = Config::from_toml("
+        rpc_url = \"http://node:8545\"
+        registry_address = \"0x5c4a1a1f5a4a1a1f5a4a1a1f5a4a1a1f5a4a1a1f\"
+        start_block = 5000000
+    ").unwrap();
+
+    assert_eq!(config.rpc_url, "http://node:8545");
+    assert_eq!(config.registry_address(), "5c4a1a1f5a4a1a1f5a4a1a1f5a4a1a1f5a4a1a1f");
+    assert_eq!(config.start_block, 5000000);
+    assert_eq!(config.confirmation_depth, Config::default().confirmation_depth);
+    assert_eq!(config.poll_interval, Config::default().poll_interval);
+    assert!(config.database_url().is_err(), "Database URL was set without being configured");
+}
+
+#[test]
+fn test_invalid_config_is_rejected() {
+    match Config::from_toml("confirmation_depth = \"twelve\"") {
+        Err(Error::Config(_)) => {},
+        _ => panic!("Config with a text confirmation depth was accepted"),
+    }
+    match parse_setting::<u64>("POLL_INTERVAL", "soon") {
+        Err(Error::Config(_)) => {},
+        _ => panic!("Text poll interval was accepted"),
+    }
+    assert_eq!(parse_setting::<u64>("POLL_INTERVAL", "5").unwrap(), 5);
+    let mut config = Config {
+        retention_days: u64::MAX,
+        ..Config::default()
+    };
+    match RetentionPolicy::from_config(&config) {
+        Err(Error::Config(_)) => {},
+        _ => panic!("Retention days too long for a date were accepted"),
+    }
+    config.retention_days = 3_652_425;
+    assert!(RetentionPolicy::from_config(&config).is_ok(), "Ten thousand years of retention were refused");
+}
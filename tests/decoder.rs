@@ -0,0 +1,129 @@
+extern crate acbidder_database;
+extern crate tiny_keccak;
+extern crate web3;
+
+use acbidder_database::decoder::*;
+
+use std::str::FromStr;
+
+use tiny_keccak::keccak256;
+use web3::types::*;
+
+//left pads a number into a single ABI word
+fn word(value: u64) -> Vec<u8> {
+    let mut word = vec![0u8; 24];
+    for shift in (0..8).rev() {
+        word.push((value >> (shift * 8)) as u8);
+    }
+    word
+}
+
+//ABI encodes a string as its length followed by the right padded bytes
+fn string_words(value: &str) -> Vec<u8> {
+    let mut words = word(value.len() as u64);
+    words.extend_from_slice(value.as_bytes());
+    while !words.len().is_multiple_of(32) {
+        words.push(0);
+    }
+    words
+}
+
+fn topic(decoder: &RegistryDecoder, name: &str) -> H256 {
+    decoder.event(name).expect("Event missing from the Registry ABI").topic()
+}
+
+//check that all ten Registry events are loaded and that topics match the ones emitted on chain
+#[test]
+fn test_registry_event_topics() {
+    let events = registry_events().unwrap();
+    assert!(events.len() == 10, "Registry ABI did not contain ten events");
+
+    let decoder = RegistryDecoder::new().unwrap();
+    let application = decoder.event("_Application").unwrap();
+    assert_eq!(application.signature(), "_Application(bytes32,uint256,string)");
+    assert_eq!(application.topic(), H256::from_str("5cde15b9901ca13a7e2eb4fb919870d1bde9e8d93d9aa5e26945b42190067bdc").unwrap());
+}
+
+//check that the dynamic data string is decoded whole, including characters the byte scanner used to drop
+#[test]
+fn test_decode_application() {
+    let decoder = RegistryDecoder::new().unwrap();
+    let domain = "ad_server-1.example.com";
+    let listing_hash = H256::from(keccak256(domain.as_bytes()));
+
+    let mut data = listing_hash.to_vec();
+    data.extend(word(100));
+    data.extend(word(96));
+    data.extend(string_words(domain));
+
+    let event = decoder.decode_event(&[topic(&decoder, "_Application")], &data).unwrap();
+    assert_eq!(event, Some(RegistryEvent::Application(Application {
+        listing_hash: listing_hash,
+        deposit: U256::from(100),
+        data: format!("{}", domain),
+    })));
+}
+
+//check that static only events and address parameters are decoded
+#[test]
+fn test_decode_reward_claimed() {
+    let decoder = RegistryDecoder::new().unwrap();
+    let mut data = vec![0u8; 12];
+    data.extend_from_slice(&[7u8; 20]);
+    data.extend(word(3));
+    data.extend(word(500));
+
+    let event = decoder.decode_event(&[topic(&decoder, "_RewardClaimed")], &data).unwrap();
+    assert_eq!(event, Some(RegistryEvent::RewardClaimed(RewardClaimed {
+        voter: H160::from(&[7u8; 20][..]),
+        challenge_id: U256::from(3),
+        reward: U256::from(500),
+    })));
+}
+
+//check that truncated data is an error and unknown topics are skipped
+#[test]
+fn test_decode_invalid_logs() {
+    let decoder = RegistryDecoder::new().unwrap();
+    let data = word(96);
+    assert!(decoder.decode_event(&[topic(&decoder, "_Application")], &data).is_err(), "Truncated log was decoded");
+    assert_eq!(decoder.decode_event(&[H256::from(keccak256(b"Unknown()"))], &data).unwrap(), None);
+}
+
+//check that an application is only trusted when its data hashes to the listing hash
+#[test]
+fn test_domain_matches_listing_hash() {
+    let mut application = Application {
+        listing_hash: H256::from(keccak256(b"example.com")),
+        deposit: U256::from(100),
+        data: format!("example.com"),
+    };
+    assert!(application.domain_matches_listing_hash(), "Matching domain was rejected");
+
+    application.data = format!("attacker.com");
+    assert!(!application.domain_matches_listing_hash(), "Mismatched domain was accepted");
+}
+
+//check that the listings getter is called with its selector and its return data is decoded
+#[test]
+fn test_encode_and_decode_listings_call() {
+    let decoder = RegistryDecoder::new().unwrap();
+    let listing_hash = H256::from(keccak256(b"example.com"));
+    let call_data = decoder.encode_listings_call(&listing_hash).unwrap();
+    assert_eq!(&call_data[..4], &keccak256(b"listings(bytes32)")[..4]);
+    assert_eq!(&call_data[4..], &listing_hash[..]);
+
+    let mut data = word(1520000000);
+    data.extend(word(1));
+    data.extend(vec![0u8; 12]);
+    data.extend_from_slice(&[9u8; 20]);
+    data.extend(word(50));
+    data.extend(word(4));
+    assert_eq!(decoder.decode_listing(&data).unwrap(), ListingState {
+        application_expiry: U256::from(1520000000),
+        whitelisted: true,
+        owner: H160::from(&[9u8; 20][..]),
+        unstaked_deposit: U256::from(50),
+        challenge_id: U256::from(4),
+    });
+}
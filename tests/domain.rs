@@ -0,0 +1,75 @@
+extern crate acbidder_database;
+
+use acbidder_database::DomainName;
+use acbidder_database::Error;
+use acbidder_database::suffix::PublicSuffixList;
+
+//names are stored lowercase and without the trailing root dot
+#[test]
+fn test_domain_name_is_canonical() {
+    assert_eq!(DomainName::new("Example.COM").unwrap().as_str(), "example.com");
+    assert_eq!(DomainName::new("example.com.").unwrap().as_str(), "example.com");
+    assert_eq!(DomainName::new("sub-domain.example.com").unwrap().as_str(), "sub-domain.example.com");
+    assert_eq!(DomainName::new("localhost").unwrap().as_str(), "localhost");
+    assert_eq!(DomainName::new("Example.com").unwrap(), DomainName::new("example.com.").unwrap());
+}
+
+//internationalized names are converted to punycode
+#[test]
+fn test_domain_name_converts_idns_to_punycode() {
+    assert_eq!(DomainName::new("bücher.de").unwrap().as_str(), "xn--bcher-kva.de");
+    assert_eq!(DomainName::new("BÜCHER.de").unwrap().as_str(), "xn--bcher-kva.de");
+    assert_eq!(DomainName::new("xn--bcher-kva.de").unwrap().as_str(), "xn--bcher-kva.de");
+    assert_eq!(DomainName::new("例え.テスト").unwrap().as_str(), "xn--r8jz45g.xn--zckzah");
+}
+
+#[test]
+fn test_improper_domain_names_are_rejected() {
+    let long_label = "a".repeat(64);
+    let long_name = format!("{}.com", vec!["a".repeat(50); 5].join("."));
+    let improper = vec![
+        "", ".", "..", "example..com", ".example.com", "example.com..",
+        "-example.com", "example-.com", "example.-com",
+        "exa mple.com", "ex_ample.com", "%.com", "*.example.com", "example.com/",
+        &long_label, &long_name,
+    ];
+    for name in improper {
+        match DomainName::new(name) {
+            Err(Error::InvalidDomain(_)) => {},
+            Ok(val) => panic!("{:?} was accepted as {}", name, val),
+            Err(e) => panic!("{:?} failed with the wrong error: {}", name, e),
+        }
+    }
+
+    //the limits themselves are allowed
+    assert!(DomainName::new(&format!("{}.com", "a".repeat(63))).is_ok(), "63 character label was rejected");
+    let longest_name = format!("{}.{}", vec!["a".repeat(63); 3].join("."), "a".repeat(61));
+    assert!(longest_name.len() == 253 && DomainName::new(&longest_name).is_ok(), "253 character name was rejected");
+}
+
+fn registrable(name: &str) -> Option<String> {
+    PublicSuffixList::bundled()
+        .registrable_domain(&DomainName::new(name).unwrap())
+        .map(|val| val.into_string())
+}
+
+//the registrable domain is the public suffix and the label in front of it
+#[test]
+fn test_registrable_domain_follows_the_public_suffix_list() {
+    assert_eq!(registrable("bid.adserver.com"), Some(format!("adserver.com")));
+    assert_eq!(registrable("us-east.bid.adserver.co.uk"), Some(format!("adserver.co.uk")));
+    assert_eq!(registrable("adserver.co.uk"), Some(format!("adserver.co.uk")));
+    assert_eq!(registrable("co.uk"), None);
+    assert_eq!(registrable("com"), None);
+    //private section suffixes count as well so customers of a platform are kept apart
+    assert_eq!(registrable("customer.github.io"), Some(format!("customer.github.io")));
+    //wildcard and exception rules, *.ck and !www.ck
+    assert_eq!(registrable("a.b.ck"), Some(format!("a.b.ck")));
+    assert_eq!(registrable("b.ck"), None);
+    assert_eq!(registrable("bid.www.ck"), Some(format!("www.ck")));
+    //rules are written in unicode in the list
+    assert_eq!(registrable("bid.adserver.公司.cn"), Some(format!("adserver.xn--55qx5d.cn")));
+    //names under a top level domain missing from the list have a single label suffix
+    assert_eq!(registrable("bid.adserver.notatld"), Some(format!("adserver.notatld")));
+    assert_eq!(PublicSuffixList::bundled().public_suffix(&DomainName::new("bid.adserver.co.uk").unwrap()).as_str(), "co.uk");
+}
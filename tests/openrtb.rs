@@ -0,0 +1,187 @@
+extern crate acbidder_database;
+extern crate chrono;
+
+use chrono::NaiveDate;
+
+use acbidder_database::Error;
+use acbidder_database::openrtb::{BidRequest, BidResponse};
+
+//banner request for a site, shortened from example 1 of the OpenRTB 2.5 specification
+const SITE_BANNER_REQUEST: &str = r#"{
+    "id": "80ce30c53c16e6ede735f123ef6e32361bfc7b22",
+    "at": 1,
+    "cur": ["USD"],
+    "imp": [{
+        "id": "1",
+        "bidfloor": 0.03,
+        "banner": {"h": 250, "w": 300, "pos": 0}
+    }],
+    "site": {
+        "id": "102855",
+        "cat": ["IAB3-1"],
+        "domain": "www.Foobar.com",
+        "page": "http://www.foobar.com/1234.html",
+        "publisher": {"id": "8953", "name": "foobar.com", "domain": "foobar.com"}
+    },
+    "device": {
+        "ua": "Mozilla/5.0",
+        "ip": "123.145.167.10",
+        "devicetype": 2,
+        "geo": {"country": "USA", "region": "CA"}
+    },
+    "user": {"id": "55816b39711f9b5acf3b90e313ed29e51665623f"},
+    "ext": {"userquality": 7}
+}"#;
+
+//every recorded field is read from the request
+#[test]
+fn test_bid_request_details_are_read() {
+    let bid_request = BidRequest::parse(SITE_BANNER_REQUEST).unwrap();
+    let received_at = NaiveDate::from_ymd_opt(2018, 4, 2).unwrap().and_hms_opt(10, 15, 30).unwrap();
+    let details = bid_request.details(received_at);
+
+    assert_eq!(bid_request.publisher().unwrap().as_str(), "www.foobar.com");
+    assert_eq!(bid_request.user_quality(), 7);
+    assert_eq!(details.received_at, Some(received_at));
+    assert_eq!(details.auction_id, Some(format!("80ce30c53c16e6ede735f123ef6e32361bfc7b22")));
+    assert_eq!(details.impressions, Some(1));
+    assert_eq!(details.ad_format, Some(format!("banner")));
+    assert_eq!(details.ad_size, Some(format!("300x250")));
+    assert_eq!(details.bid_floor_micros, Some(30000));
+    assert_eq!(details.bid_floor_currency, Some(format!("USD")));
+    assert_eq!(details.page_url, Some(format!("http://www.foobar.com/1234.html")));
+    assert_eq!(details.device_type, Some(2));
+    assert_eq!(details.region, Some(format!("USA-CA")));
+    let user_id_hash = details.user_id_hash.unwrap();
+    assert!(user_id_hash.len() == 64 && !user_id_hash.contains("55816b39711f9b5acf3b90e313ed29e51665623f"), "User id was not hashed");
+}
+
+//apps are named through their publisher when they have no domain and missing fields are left empty
+#[test]
+fn test_bid_request_for_an_app_without_optional_fields() {
+    let bid_request = BidRequest::parse(r#"{
+        "id": "app-auction",
+        "imp": [
+            {"id": "1", "video": {"w": 640, "h": 480, "mimes": ["video/mp4"]}, "bidfloor": 1.5, "bidfloorcur": "EUR"},
+            {"id": "2", "banner": {"format": [{"w": 320, "h": 50}]}}
+        ],
+        "app": {"bundle": "com.example.game", "publisher": {"domain": "example.com"}}
+    }"#).unwrap();
+    let details = bid_request.details(NaiveDate::from_ymd_opt(2018, 4, 2).unwrap().and_hms_opt(0, 0, 0).unwrap());
+
+    assert_eq!(bid_request.publisher().unwrap().as_str(), "example.com");
+    assert_eq!(bid_request.user_quality(), 0);
+    assert_eq!(details.impressions, Some(2));
+    assert_eq!(details.ad_format, Some(format!("video")));
+    assert_eq!(details.ad_size, Some(format!("640x480")));
+    assert_eq!(details.bid_floor_micros, Some(1500000));
+    assert_eq!(details.bid_floor_currency, Some(format!("EUR")));
+    assert_eq!(details.page_url, None);
+    assert_eq!(details.device_type, None);
+    assert_eq!(details.user_id_hash, None);
+    assert_eq!(details.region, None);
+}
+
+#[test]
+fn test_improper_bid_requests_are_rejected() {
+    match BidRequest::parse(r#"{"imp": []}"#) {
+        Err(Error::Decode(_)) => {},
+        _ => panic!("Bid request without an id was accepted"),
+    };
+    match BidRequest::parse("not json") {
+        Err(Error::Decode(_)) => {},
+        _ => panic!("Bid request that is not JSON was accepted"),
+    };
+    match BidRequest::parse(r#"{"id": "no-publisher", "imp": []}"#).unwrap().publisher() {
+        Err(Error::Decode(_)) => {},
+        _ => panic!("Bid request without a publisher was accepted"),
+    };
+    match BidRequest::parse(r#"{"id": "bad-publisher", "site": {"domain": "foo_bar.com"}}"#).unwrap().publisher() {
+        Err(Error::InvalidDomain(_)) => {},
+        _ => panic!("Bid request with an improper publisher was accepted"),
+    };
+}
+
+//strings that would not fit the columns they are recorded in are rejected before they reach the database
+#[test]
+fn test_bid_request_fields_too_long_are_rejected() {
+    let long_id = format!(r#"{{"id": "{}"}}"#, "a".repeat(256));
+    let requests = [
+        long_id.as_str(),
+        r#"{"id": "1", "imp": [{"bidfloor": 1, "bidfloorcur": "EURO"}]}"#,
+        r#"{"id": "1", "device": {"geo": {"country": "USA", "region": "Californiaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"}}}"#,
+    ];
+    for json in requests.iter() {
+        match BidRequest::parse(json) {
+            Err(Error::Decode(_)) => {},
+            _ => panic!("Bid request with a field too long was accepted: {}", json),
+        };
+    }
+    let euro = BidRequest::parse(r#"{"id": "1", "imp": [{"bidfloor": 1, "bidfloorcur": "€€€"}]}"#);
+    assert!(euro.is_ok(), "Currency was measured in bytes");
+}
+
+//every bid of every seat becomes a response
+#[test]
+fn test_bid_response_details_are_read() {
+    let bid_response = BidResponse::parse(r#"{
+        "id": "1234567890",
+        "bidid": "abc1123",
+        "cur": "EUR",
+        "seatbid": [
+            {"seat": "512", "bid": [
+                {"id": "1", "impid": "102", "price": 9.43, "adid": "314", "crid": "1234", "dealid": "deal-1"},
+                {"id": "2", "impid": "103", "price": 1.2}
+            ]},
+            {"bid": [{"id": "3", "impid": "102", "price": 0.000001, "crid": "5678"}]}
+        ]
+    }"#).unwrap();
+    let details = bid_response.details(85);
+
+    assert_eq!(bid_response.id, "1234567890");
+    assert_eq!(details.len(), 3);
+    assert_eq!(details[0].seat, Some(format!("512")));
+    assert_eq!(details[0].bid_price_micros, Some(9430000));
+    assert_eq!(details[0].bid_currency, Some(format!("EUR")));
+    assert_eq!(details[0].creative_id, Some(format!("1234")));
+    assert_eq!(details[0].deal_id, Some(format!("deal-1")));
+    assert_eq!(details[0].latency_ms, Some(85));
+    assert_eq!(details[1].seat, Some(format!("512")));
+    assert_eq!(details[1].creative_id, None);
+    assert_eq!(details[2].seat, None);
+    assert_eq!(details[2].bid_price_micros, Some(1));
+}
+
+//a response without bids is a single no-bid and prices default to USD
+#[test]
+fn test_bid_response_without_bids_is_a_no_bid() {
+    let details = BidResponse::parse(r#"{"id": "1234567890", "nbr": 2}"#).unwrap().details(12);
+    assert_eq!(details.len(), 1);
+    assert_eq!(details[0].bid_price_micros, None);
+    assert_eq!(details[0].latency_ms, Some(12));
+
+    let details = BidResponse::parse(r#"{"id": "1", "seatbid": [{"bid": [{"price": 2}]}]}"#).unwrap().details(12);
+    assert_eq!(details[0].bid_currency, Some(format!("USD")));
+
+    match BidResponse::parse(r#"{"id": "1", "seatbid": [{"bid": [{"crid": "no price"}]}]}"#) {
+        Err(Error::Decode(_)) => {},
+        _ => panic!("Bid without a price was accepted"),
+    };
+}
+
+#[test]
+fn test_bid_response_fields_too_long_are_rejected() {
+    let long_seat = format!(r#"{{"id": "1", "seatbid": [{{"seat": "{}", "bid": [{{"price": 1}}]}}]}}"#, "s".repeat(65));
+    let long_deal = format!(r#"{{"id": "1", "seatbid": [{{"bid": [{{"price": 1, "dealid": "{}"}}]}}]}}"#, "d".repeat(256));
+    let responses = [
+        r#"{"id": "1", "cur": "EURO"}"#,
+        long_seat.as_str(),
+        long_deal.as_str(),
+    ];
+    for json in responses.iter() {
+        match BidResponse::parse(json) {
+            Err(Error::Decode(_)) => {},
+            _ => panic!("Bid response with a field too long was accepted: {}", json),
+        };
+    }
+}
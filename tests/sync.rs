@@ -0,0 +1,312 @@
+extern crate acbidder_database;
+extern crate diesel;
+extern crate tiny_keccak;
+extern crate web3;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use tiny_keccak::keccak256;
+use web3::types::*;
+
+use acbidder_database::{establish_connection, run_migrations, DbConnection, Error};
+use acbidder_database::{Chain, RegistrySync};
+use acbidder_database::decoder::RegistryDecoder;
+use acbidder_database::models::ListingStatus;
+use acbidder_database::schema;
+use acbidder_database::{is_whitelisted, get_whitelisted_domains, get_listings, get_registry_listing, get_processed_log, load_pending_applications, get_rejected_applications};
+use acbidder_database::DomainName;
+
+//a chain the test moves forward and reorganizes, the registry contract is never reachable on it
+struct TestChain {
+    head: RefCell<u64>,
+    hashes: RefCell<HashMap<u64, H256>>,
+}
+
+impl TestChain {
+    fn new(head: u64) -> TestChain {
+        TestChain {
+            head: RefCell::new(head),
+            hashes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn set_head(&self, head: u64) {
+        *self.head.borrow_mut() = head;
+    }
+
+    //puts the block on the fork at that height
+    fn set_block(&self, block: u64, fork: u8) {
+        self.hashes.borrow_mut().insert(block, block_hash(block, fork));
+    }
+}
+
+impl Chain for TestChain {
+    fn head(&self) -> Result<u64, Error> {
+        Ok(*self.head.borrow())
+    }
+
+    fn block_hash(&self, block: u64) -> Result<Option<H256>, Error> {
+        Ok(self.hashes.borrow().get(&block).cloned())
+    }
+
+    fn call_registry(&self, _registry_address: &str, _data: Vec<u8>, _block: u64) -> Result<Vec<u8>, Error> {
+        Err(Error::Decode(format!("The test chain has no registry contract")))
+    }
+}
+
+fn block_hash(block: u64, fork: u8) -> H256 {
+    H256::from(keccak256(format!("block {} on fork {}", block, fork).as_bytes()))
+}
+
+//left pads a number into a single ABI word
+fn word(value: u64) -> Vec<u8> {
+    let mut word = vec![0u8; 24];
+    for shift in (0..8).rev() {
+        word.push((value >> (shift * 8)) as u8);
+    }
+    word
+}
+
+//ABI encodes a string as its length followed by the right padded bytes
+fn string_words(value: &str) -> Vec<u8> {
+    let mut words = word(value.len() as u64);
+    words.extend_from_slice(value.as_bytes());
+    while !words.len().is_multiple_of(32) {
+        words.push(0);
+    }
+    words
+}
+
+//a Registry log mined in the block on the fork
+fn registry_log(event: &str, data: Vec<u8>, block: u64, index: u64, fork: u8) -> Log {
+    let decoder = RegistryDecoder::new().unwrap();
+    Log {
+        address: H160::default(),
+        topics: vec![decoder.event(event).expect("Event missing from the Registry ABI").topic()],
+        data: Bytes(data),
+        block_hash: Some(block_hash(block, fork)),
+        block_number: Some(U256::from(block)),
+        transaction_hash: Some(H256::from(keccak256(format!("transaction {} {} on fork {}", block, index, fork).as_bytes()))),
+        transaction_index: None,
+        log_index: Some(U256::from(index)),
+        transaction_log_index: None,
+        log_type: None,
+        removed: None,
+    }
+}
+
+fn application_log(listing_hash: &H256, domain: &str, block: u64, index: u64, fork: u8) -> Log {
+    let mut data = listing_hash.to_vec();
+    data.extend(word(100));
+    data.extend(word(96));
+    data.extend(string_words(domain));
+    registry_log("_Application", data, block, index, fork)
+}
+
+fn whitelisted_log(listing_hash: &H256, block: u64, index: u64, fork: u8) -> Log {
+    registry_log("_NewListingWhitelisted", listing_hash.to_vec(), block, index, fork)
+}
+
+fn listing_hash(domain: &str) -> H256 {
+    H256::from(keccak256(domain.as_bytes()))
+}
+
+fn hex(hash: &H256) -> String {
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn establish_test_connection() -> DbConnection {
+    let connection = establish_connection().unwrap();
+    run_migrations(&connection).unwrap();
+    connection
+}
+
+//removes what the sync wrote for the registry and its domains so the test starts and ends with nothing
+fn reset_registry(conn: &DbConnection, registry_address: &str, domains: &[&str]) {
+    use schema::{listings, pending_applications, processed_logs, registry_listings, rejected_applications, sync_state};
+
+    diesel::delete(sync_state::table.filter(sync_state::registry.eq(registry_address))).execute(conn).unwrap();
+    diesel::delete(pending_applications::table.filter(pending_applications::registry.eq(registry_address))).execute(conn).unwrap();
+    diesel::delete(processed_logs::table.filter(processed_logs::registry.eq(registry_address))).execute(conn).unwrap();
+    diesel::delete(rejected_applications::table.filter(rejected_applications::registry.eq(registry_address))).execute(conn).unwrap();
+    let hashes: Vec<String> = domains.iter().map(|domain| hex(&listing_hash(domain))).collect();
+    diesel::delete(registry_listings::table.filter(registry_listings::domain.eq_any(domains))).execute(conn).unwrap();
+    diesel::delete(registry_listings::table.filter(registry_listings::listing_hash.eq_any(&hashes))).execute(conn).unwrap();
+    diesel::delete(listings::table.filter(listings::domain.eq_any(domains))).execute(conn).unwrap();
+}
+
+fn whitelisted(conn: &DbConnection, domain: &str) -> bool {
+    is_whitelisted(conn, &DomainName::new(domain).unwrap()).unwrap()
+}
+
+fn status(conn: &DbConnection, hash: &H256) -> Option<String> {
+    get_registry_listing(conn, &hex(hash)).unwrap().map(|listing| listing.status)
+}
+
+//check that logs are only applied once they are buried under the confirmation depth
+#[test]
+fn test_shallow_logs_wait_for_confirmation() {
+    let registry = "5100000000000000000000000000000000000001";
+    let domain = "confirmed.sync.com";
+    let connection = establish_test_connection();
+    reset_registry(&connection, registry, &[domain]);
+    let chain = TestChain::new(10);
+    chain.set_block(9, 0);
+
+    let mut registry_sync = RegistrySync::load(&connection, registry, 3, 0, None).unwrap();
+    registry_sync.observe(application_log(&listing_hash(domain), domain, 9, 0, 0)).unwrap();
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 0, "Log shallower than the confirmation depth was applied");
+    assert!(status(&connection, &listing_hash(domain)).is_none(), "Unconfirmed application was recorded");
+
+    chain.set_head(12);
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 1, "Confirmed log was not applied");
+    assert_eq!(status(&connection, &listing_hash(domain)), Some(ListingStatus::Applied.as_str().to_string()));
+
+    reset_registry(&connection, registry, &[domain]);
+}
+
+//check that a fork below the checkpoint undoes the logs of the abandoned blocks and applies the ones that replace them
+#[test]
+fn test_fork_below_checkpoint_rolls_listings_back() {
+    let registry = "5100000000000000000000000000000000000002";
+    let domain = "forked.sync.com";
+    let connection = establish_test_connection();
+    reset_registry(&connection, registry, &[domain]);
+    let chain = TestChain::new(20);
+    chain.set_block(5, 0);
+    chain.set_block(6, 0);
+
+    let mut registry_sync = RegistrySync::load(&connection, registry, 1, 0, None).unwrap();
+    registry_sync.observe(application_log(&listing_hash(domain), domain, 5, 0, 0)).unwrap();
+    registry_sync.observe(whitelisted_log(&listing_hash(domain), 6, 0, 0)).unwrap();
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 2, "Logs were not applied");
+    assert!(whitelisted(&connection, domain), "Whitelisted listing was not whitelisted");
+
+    //block 6 is replaced by a block without the whitelisting, which moves to block 7
+    chain.set_block(6, 1);
+    chain.set_block(7, 1);
+    registry_sync.check_for_reorg(&chain).unwrap();
+    assert!(!whitelisted(&connection, domain), "Whitelisting from an abandoned block was kept");
+    assert_eq!(status(&connection, &listing_hash(domain)), Some(ListingStatus::Applied.as_str().to_string()));
+    assert!(get_processed_log(&connection, registry, 6, 0).unwrap().is_none(), "Abandoned log stayed processed");
+    assert!(get_processed_log(&connection, registry, 5, 0).unwrap().is_some(), "Log below the fork was undone");
+    assert!(registry_sync.from_block() == 5, "Checkpoint was not rewound to the block before the fork");
+
+    //a log still queued from the abandoned block is dropped while its replacement is applied
+    registry_sync.observe(whitelisted_log(&listing_hash(domain), 6, 0, 0)).unwrap();
+    registry_sync.observe(whitelisted_log(&listing_hash(domain), 7, 0, 1)).unwrap();
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 1, "Only the replacement log should be applied");
+    assert!(whitelisted(&connection, domain), "Replacement whitelisting was not applied");
+
+    reset_registry(&connection, registry, &[domain]);
+}
+
+//check that a log seen again, by the same sync or after a restart, is not applied twice
+#[test]
+fn test_replayed_log_is_idempotent() {
+    let registry = "5100000000000000000000000000000000000003";
+    let domain = "replayed.sync.com";
+    let connection = establish_test_connection();
+    reset_registry(&connection, registry, &[domain]);
+    let chain = TestChain::new(20);
+    chain.set_block(5, 0);
+    chain.set_block(6, 0);
+    let logs = vec![application_log(&listing_hash(domain), domain, 5, 0, 0), whitelisted_log(&listing_hash(domain), 6, 0, 0)];
+
+    let mut registry_sync = RegistrySync::load(&connection, registry, 1, 0, None).unwrap();
+    for log in logs.clone() {
+        registry_sync.observe(log).unwrap();
+    }
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 2, "Logs were not applied");
+    for log in logs.clone() {
+        registry_sync.observe(log).unwrap();
+    }
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 0, "Processed log was applied again");
+
+    //a restarted sync reads from its checkpoint block, which replays the last log it applied
+    let mut restarted = RegistrySync::load(&connection, registry, 1, 0, None).unwrap();
+    assert!(restarted.from_block() == 6, "Checkpoint was not restored");
+    for log in logs {
+        restarted.observe(log).unwrap();
+    }
+    assert!(restarted.commit_confirmed(&chain).unwrap() == 0, "Processed log was applied again after a restart");
+    assert!(whitelisted(&connection, domain), "Replay changed the listing");
+    assert!(load_pending_applications(&connection, registry).unwrap().len() == 1, "Replay changed the pending applications");
+
+    reset_registry(&connection, registry, &[domain]);
+}
+
+fn listed(conn: &DbConnection, domain: &str) -> bool {
+    get_listings(conn).unwrap().iter().any(|ad_server| ad_server.domain == domain)
+}
+
+//check that an application whose data does not hash to its listing hash is rejected instead of listed
+#[test]
+fn test_mismatched_application_is_rejected() {
+    let registry = "5100000000000000000000000000000000000004";
+    let domain = "victim.sync.com";
+    let connection = establish_test_connection();
+    reset_registry(&connection, registry, &[domain]);
+    let chain = TestChain::new(20);
+    chain.set_block(5, 0);
+
+    let mut registry_sync = RegistrySync::load(&connection, registry, 1, 0, None).unwrap();
+    registry_sync.observe(application_log(&listing_hash("attacker.sync.com"), domain, 5, 0, 0)).unwrap();
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 1, "Log was not applied");
+
+    let rejected = get_rejected_applications(&connection, registry).unwrap();
+    assert!(rejected.len() == 1, "Mismatched application was not rejected");
+    assert_eq!(rejected[0].listing_hash, hex(&listing_hash("attacker.sync.com")));
+    assert!(rejected[0].block_number == 5 && rejected[0].log_index == 0, "Position of the rejected application was not recorded");
+    assert!(load_pending_applications(&connection, registry).unwrap().is_empty(), "Mismatched application was kept pending");
+    assert!(!listed(&connection, domain), "Mismatched application was listed");
+    assert_eq!(get_processed_log(&connection, registry, 5, 0).unwrap().unwrap().action, "rejected");
+
+    reset_registry(&connection, registry, &[domain]);
+}
+
+//check that a whitelisting of a listing hash with no valid application adds nothing to the listings table
+#[test]
+fn test_whitelisting_unknown_listing_is_skipped() {
+    let registry = "5100000000000000000000000000000000000005";
+    let domain = "unknown.sync.com";
+    let connection = establish_test_connection();
+    reset_registry(&connection, registry, &[domain]);
+    let chain = TestChain::new(20);
+    chain.set_block(5, 0);
+
+    let mut registry_sync = RegistrySync::load(&connection, registry, 1, 0, None).unwrap();
+    registry_sync.observe(whitelisted_log(&listing_hash(domain), 5, 0, 0)).unwrap();
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 1, "Log was not applied");
+    assert!(!listed(&connection, domain), "Unknown listing was listed");
+    assert_eq!(get_processed_log(&connection, registry, 5, 0).unwrap().unwrap().action, "none");
+
+    reset_registry(&connection, registry, &[domain]);
+}
+
+//check that the chain whitelisting a rejected or unknown listing hash never whitelists a domain
+#[test]
+fn test_rejected_listing_is_never_whitelisted() {
+    let registry = "5100000000000000000000000000000000000006";
+    let domain = "spoofed.sync.com";
+    let connection = establish_test_connection();
+    reset_registry(&connection, registry, &[domain, "attacker.sync.com", "unknown.spoofed.sync.com"]);
+    let chain = TestChain::new(20);
+    chain.set_block(5, 0);
+    chain.set_block(6, 0);
+
+    let mut registry_sync = RegistrySync::load(&connection, registry, 1, 0, None).unwrap();
+    registry_sync.observe(application_log(&listing_hash("attacker.sync.com"), domain, 5, 0, 0)).unwrap();
+    registry_sync.observe(whitelisted_log(&listing_hash("attacker.sync.com"), 6, 0, 0)).unwrap();
+    registry_sync.observe(whitelisted_log(&listing_hash("unknown.spoofed.sync.com"), 6, 1, 0)).unwrap();
+    assert!(registry_sync.commit_confirmed(&chain).unwrap() == 3, "Logs were not applied");
+
+    assert_eq!(status(&connection, &listing_hash("attacker.sync.com")), Some(ListingStatus::Rejected.as_str().to_string()));
+    assert!(status(&connection, &listing_hash("unknown.spoofed.sync.com")).is_none(), "Unknown listing was recorded");
+    assert!(!whitelisted(&connection, domain), "Domain of a rejected application was whitelisted");
+    assert!(get_whitelisted_domains(&connection).unwrap().iter().all(|whitelisted| whitelisted != domain && !whitelisted.is_empty()), "Unverified domain was whitelisted");
+
+    reset_registry(&connection, registry, &[domain, "attacker.sync.com", "unknown.spoofed.sync.com"]);
+}
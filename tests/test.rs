@@ -1,20 +1,103 @@
 extern crate acbidder_database;
+extern crate chrono;
 extern crate diesel;
+extern crate flate2;
 extern crate web3;
 
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::process;
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Duration as TimeDelta, Utc};
+use flate2::read::GzDecoder;
+
 use acbidder_database::establish_connection;
+use acbidder_database::run_migrations;
+use acbidder_database::applied_migrations;
+use acbidder_database::DbConnection;
+use acbidder_database::Error;
+use acbidder_database::Config;
+use acbidder_database::DomainName;
+use acbidder_database::WhitelistCache;
+use acbidder_database::WhitelistChange;
+use acbidder_database::cache::keep_up_to_date;
+use acbidder_database::create_pool;
+use acbidder_database::get_connection;
+use acbidder_database::api::serve;
+use acbidder_database::{apply_retention, RetentionPolicy};
 
 use acbidder_database::create_listing;
 use acbidder_database::is_whitelisted;
+use acbidder_database::is_whitelisted_host;
 use acbidder_database::delete_listing;
+use acbidder_database::search_listings;
 
 use acbidder_database::create_request;
+use acbidder_database::create_requests;
 use acbidder_database::get_latest_request_id;
+use acbidder_database::get_request;
+use acbidder_database::ingest_openrtb_request;
 use acbidder_database::delete_request;
+use acbidder_database::prune_requests;
+use acbidder_database::requests_between;
+use acbidder_database::count_requests_between;
 
 use acbidder_database::create_response;
+use acbidder_database::create_responses;
 use acbidder_database::get_latest_response_id;
 use acbidder_database::delete_response;
+use acbidder_database::prune_responses;
+use acbidder_database::get_response;
+use acbidder_database::ingest_openrtb_response;
+use acbidder_database::record_win;
+use acbidder_database::record_loss;
+use acbidder_database::responses_for_request;
+use acbidder_database::unanswered_requests;
+use acbidder_database::responses_between;
+use acbidder_database::count_responses_between;
+
+use acbidder_database::get_sync_state;
+use acbidder_database::update_sync_state;
+use acbidder_database::load_pending_applications;
+use acbidder_database::save_pending_application;
+use acbidder_database::delete_pending_application;
+
+use acbidder_database::record_processed_log;
+use acbidder_database::get_processed_log;
+use acbidder_database::processed_logs_from;
+use acbidder_database::delete_processed_logs_from;
+
+use acbidder_database::create_rejected_application;
+use acbidder_database::get_rejected_applications;
+use acbidder_database::delete_rejected_applications_from;
+
+use acbidder_database::domain_listing_hash;
+use acbidder_database::get_registry_listing_by_domain;
+use acbidder_database::get_registry_listings_with_status;
+use acbidder_database::save_registry_listing;
+use acbidder_database::delete_registry_listing;
+use acbidder_database::delete_registry_listing_domain;
+use acbidder_database::create_registry_listing_domain;
+
+use acbidder_database::create_challenge;
+use acbidder_database::get_challenge;
+use acbidder_database::resolve_challenge;
+use acbidder_database::challenges_for_domain;
+use acbidder_database::open_challenges;
+use acbidder_database::delete_challenges_from;
+use acbidder_database::reopen_challenges_from;
+use acbidder_database::create_reward_claim;
+use acbidder_database::reward_claims_for_challenge;
+use acbidder_database::delete_reward_claims_from;
+
+use acbidder_database::create_stake_change;
+use acbidder_database::get_listing_stake;
+use acbidder_database::delete_stake_changes_from;
 
 use acbidder_database::models::*;
 use acbidder_database::schema::listings::dsl::*;
@@ -25,6 +108,29 @@ use diesel::RunQueryDsl;
 use acbidder_database::current_auto_increment_value_responses;
 use acbidder_database::current_auto_increment_value_requests;
 
+//connects to DATABASE_URL and creates any missing tables so the suite also runs with DATABASE_URL=:memory: on sqlite
+fn establish_test_connection() -> DbConnection {
+    let connection = establish_connection().unwrap();
+    run_migrations(&connection).unwrap();
+    connection
+}
+
+//names used in the tests are all valid domains
+fn domain_name(name: &str) -> DomainName {
+    DomainName::new(name).unwrap()
+}
+
+//sends a GET to the API and returns the status line and the body
+fn http_get(address: SocketAddr, path: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {} HTTP/1.0\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response.lines().next().unwrap_or("").to_string();
+    let body = response.split_once("\r\n\r\n").map(|(_, body)| body).unwrap_or("").to_string();
+    (status, body)
+}
+
 
 ///---------------------------------------------------------------------
 ///NOTE: Tests must be run with a clean table and using -- --test-threads=1
@@ -34,8 +140,8 @@ use acbidder_database::current_auto_increment_value_requests;
 //check that the ad_server is being added to listing table
 #[test]
 fn test_1_add_ad_server_to_listing() {
-    let connection = establish_connection();
-    let creation = create_listing(&connection, "first.com").unwrap();
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("first.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
 
     let results = listings
@@ -55,14 +161,13 @@ fn test_1_add_ad_server_to_listing() {
 //check that the ad_server with the same name cannot be added to listing
 #[test]
 fn test_2_add_invalid_ad_server_to_listing() {
-    let connection = establish_connection();
-    let creation = create_listing(&connection, "second.com").unwrap();
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("second.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
-    match create_listing(&connection, "second.com"){
+    match create_listing(&connection, &domain_name("second.com")){
         Ok(_) => panic!("Insert succeeded when it was supposed to fail."),
-        Err(e) => if e != "Insert for Listing failed." {
-            panic!("Repeat insertion succeeded.");
-        },
+        Err(Error::DuplicateListing(_)) => {},
+        Err(e) => panic!("Repeat insertion failed with the wrong error: {}", e),
     };
 
     let results = listings
@@ -82,8 +187,8 @@ fn test_2_add_invalid_ad_server_to_listing() {
 //check that listing is removed properly
 #[test]
 fn test_3_add_and_remove_ad_server_from_listing() {
-    let connection = establish_connection();
-    let creation = create_listing(&connection, "third.com").unwrap();
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("third.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
 
     let deletion = delete_listing(&connection, format!("third.com")).unwrap();
@@ -101,35 +206,35 @@ fn test_4() {
 //check that special characters cannot be used as a domain name
 #[test]
 fn test_5_add_invalid_ad_server_from_listing_using_special_characters() {
-    let connection = establish_connection();
-    match create_listing(&connection, "*fifth.com"){
-        Ok(_) => panic!("Create listing succeeded when it was supposed to fail."),
-        Err(e) => if e != "Improper domain name *fifth.com" {
-            panic!("Insertion with special character * succeeded.");
-        },
+    let connection = establish_test_connection();
+    match DomainName::new("*fifth.com"){
+        Ok(_) => panic!("Domain name was accepted when it was supposed to fail."),
+        Err(Error::InvalidDomain(_)) => {},
+        Err(_) => panic!("Domain name with special character * succeeded."),
     };
-    match create_listing(&connection, "the_fifth.com"){
-        Ok(_) => panic!("Create listing succeeded when it was supposed to fail."),
-        Err(e) => if e != "Improper domain name the_fifth.com" {
-            panic!("Insertion with special character _ succeeded.");
-        },
+    match DomainName::new("the_fifth.com"){
+        Ok(_) => panic!("Domain name was accepted when it was supposed to fail."),
+        Err(Error::InvalidDomain(_)) => {},
+        Err(_) => panic!("Domain name with special character _ succeeded."),
     };
-    match create_listing(&connection, "%fifth.com"){
-        Ok(_) => panic!("Create listing succeeded when it was supposed to fail."),
-        Err(e) => if e != "Improper domain name %fifth.com" {
-            panic!("Insertion with special character % succeeded.");
-        },
+    match DomainName::new("%fifth.com"){
+        Ok(_) => panic!("Domain name was accepted when it was supposed to fail."),
+        Err(Error::InvalidDomain(_)) => {},
+        Err(_) => panic!("Domain name with special character % succeeded."),
     };
 
-    let deletion = delete_listing(&connection, format!("%fifth.com")).unwrap();
-    assert!(deletion == 0, "Deletion succeeded when there are no matches.");
+    match delete_listing(&connection, format!("%fifth.com")){
+        Ok(_) => panic!("Delete listing succeeded when it was supposed to fail."),
+        Err(Error::InvalidDomain(_)) => {},
+        Err(_) => panic!("Deletion with special character % succeeded."),
+    };
 }
 
 //sixth.com
 //check that a non-existing domain cannot be deleted and returns proper response
 #[test]
 fn test_6_invalid_remove_ad_server_from_listing() {
-    let connection = establish_connection();
+    let connection = establish_test_connection();
     let deletion = delete_listing(&connection, format!("definitelyNotReal.com")).unwrap();
     assert!(deletion == 0, "Deletion succeeded when there are no matches.");
 }
@@ -138,11 +243,11 @@ fn test_6_invalid_remove_ad_server_from_listing() {
 //check if the whitelist function works to find a specified domain
 #[test]
 fn test_7_add_ad_server_to_listing_and_valid_whitelist() {
-    let connection = establish_connection();
-    let creation = create_listing(&connection, "seventh.com").unwrap();
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("seventh.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
 
-    let is_whitelisted = is_whitelisted(&connection, format!("seventh.com")).unwrap();
+    let is_whitelisted = is_whitelisted(&connection, &domain_name("seventh.com")).unwrap();
     assert!(
         is_whitelisted,
         "is_whitelisted returned false when entry exists"
@@ -156,11 +261,11 @@ fn test_7_add_ad_server_to_listing_and_valid_whitelist() {
 //check the whitelist function for a false positive
 #[test]
 fn test_8_add_ad_server_to_listing_and_invalid_whitelist() {
-    let connection = establish_connection();
-    let creation = create_listing(&connection, "eighth.com").unwrap();
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("eighth.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
 
-    let is_whitelisted = is_whitelisted(&connection, format!("Noteighth.com")).unwrap();
+    let is_whitelisted = is_whitelisted(&connection, &domain_name("Noteighth.com")).unwrap();
     assert!(
         !is_whitelisted,
         "is_whitelisted returned true when entry does not exits"
@@ -174,14 +279,14 @@ fn test_8_add_ad_server_to_listing_and_invalid_whitelist() {
 //check that multiple listings can be loaded
 #[test]
 fn test_9_add_ad_server_to_listing_and_show_ad_servers() {
-    let connection = establish_connection();
-    let creation = create_listing(&connection, "anineth.com").unwrap();
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("anineth.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
-    let creation = create_listing(&connection, "bnineth.com").unwrap();
+    let creation = create_listing(&connection, &domain_name("bnineth.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
-    let creation = create_listing(&connection, "cnineth.com").unwrap();
+    let creation = create_listing(&connection, &domain_name("cnineth.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
-    let creation = create_listing(&connection, "dnineth.com").unwrap();
+    let creation = create_listing(&connection, &domain_name("dnineth.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
 
     let results = listings
@@ -207,48 +312,59 @@ fn test_9_add_ad_server_to_listing_and_show_ad_servers() {
         iteration += 1;
     }
 
-    let deletion = delete_listing(&connection, format!("%nineth.com")).unwrap();
-    assert!(deletion == 4, "Deletion failed");
+    let found = search_listings(&connection, "%nineth.com").unwrap();
+    assert!(found.len() == 4, "Search did not find every listing");
+    assert_eq!(found[0].domain, format!("anineth.com"));
+    assert_eq!(found[3].domain, format!("dnineth.com"));
+
+    for ad_server in found {
+        let deletion = delete_listing(&connection, ad_server.domain).unwrap();
+        assert!(deletion == 1, "Deletion failed");
+    }
 }
 
 //tenth.com
-//check that special character deletion works
+//check that special characters cannot be used to delete listings and that names are matched regardless of case
 #[test]
 fn test_10_add_ad_server_to_listing_and_special_character_deletion() {
-    let connection = establish_connection();
-    let creation = create_listing(&connection, "tenth.com").unwrap();
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("tenth.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
 
-    let deletion = delete_listing(&connection, format!("%.com")).unwrap();
-    assert!(deletion == 1, "Deletion failed. There may be persisting entries from before.");
-
-    let creation = create_listing(&connection, "tenth.com").unwrap();
-    assert!(creation == 1, "Insertion failed");
+    match delete_listing(&connection, format!("%.com")){
+        Ok(_) => panic!("Delete listing succeeded when it was supposed to fail."),
+        Err(Error::InvalidDomain(_)) => {},
+        Err(_) => panic!("Deletion with special character % succeeded."),
+    };
+    match delete_listing(&connection, format!("te_th.com")){
+        Ok(_) => panic!("Delete listing succeeded when it was supposed to fail."),
+        Err(Error::InvalidDomain(_)) => {},
+        Err(_) => panic!("Deletion with special character _ succeeded."),
+    };
+    assert!(is_whitelisted(&connection, &domain_name("tenth.com")).unwrap(), "Listing was deleted by a pattern");
 
-    let deletion = delete_listing(&connection, format!("te_th.com")).unwrap();
+    let deletion = delete_listing(&connection, format!("Tenth.COM.")).unwrap();
     assert!(deletion == 1, "Deletion failed");
 }
 
 //eleventh.com
-//check that special characters cannot be used on is_whitelist function
+//check that special characters cannot be used to look up a domain on the whitelist
 #[test]
 fn test_11_add_ad_server_to_listing_and_special_character_whitelisted() {
-    let connection = establish_connection();
-    let creation = create_listing(&connection, "eleventh.com").unwrap();
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("eleventh.com")).unwrap();
     assert!(creation == 1, "Insertion failed");
 
-    match is_whitelisted(&connection, format!("_leventh.com")){
-        Ok(_) => panic!("is_whitelisted succeeded when it was supposed to fail"),
-        Err(e) => if e != "Improper domain name _leventh.com" {
-            panic!("is_whitelisted returned true when entry does not exits");
-        },
+    match DomainName::new("_leventh.com"){
+        Ok(_) => panic!("Domain name was accepted when it was supposed to fail"),
+        Err(Error::InvalidDomain(_)) => {},
+        Err(_) => panic!("Domain name failed with the wrong error"),
     };
 
-    match is_whitelisted(&connection, format!("%venth.com")){
-        Ok(_) => panic!("is_whitelisted succeeded when it was supposed to fail"),
-        Err(e) => if e != "Improper domain name %venth.com" {
-            panic!("is_whitelisted returned true when entry does not exits");
-        },
+    match DomainName::new("%venth.com"){
+        Ok(_) => panic!("Domain name was accepted when it was supposed to fail"),
+        Err(Error::InvalidDomain(_)) => {},
+        Err(_) => panic!("Domain name failed with the wrong error"),
     };
 
     let deletion = delete_listing(&connection, format!("eleventh.com")).unwrap();
@@ -259,9 +375,9 @@ fn test_11_add_ad_server_to_listing_and_special_character_whitelisted() {
 //
 #[test]
 fn test_12_add_and_remove_request () {
-    let connection = establish_connection();
+    let connection = establish_test_connection();
     let auto_increment_value = current_auto_increment_value_requests(&connection).unwrap();
-    let creation = create_request(&connection, "twelfth.com", 5).unwrap();
+    let creation = create_request(&connection, &domain_name("twelfth.com"), 5).unwrap();
     assert!(creation == auto_increment_value as i32, "Insertion failed");
     let deletion = delete_request(&connection, format!("twelfth.com")).unwrap();
     assert!(deletion == 1, "Deletion failed");
@@ -271,9 +387,9 @@ fn test_12_add_and_remove_request () {
 //
 #[test]
 fn test_13_add_and_remove_request_check_id () {
-    let connection = establish_connection();
+    let connection = establish_test_connection();
     let auto_increment_value = current_auto_increment_value_requests(&connection).unwrap();
-    let creation = create_request(&connection, "thirteenth.com", 2).unwrap();
+    let creation = create_request(&connection, &domain_name("thirteenth.com"), 2).unwrap();
     assert!(creation == auto_increment_value as i32, "Insertion failed");
 
     let identification_value = get_latest_request_id(&connection).unwrap();
@@ -287,21 +403,29 @@ fn test_13_add_and_remove_request_check_id () {
 //
 #[test]
 fn test_14_add_and_remove_response () {
-    let connection = establish_connection();
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("fourteenth.com")).unwrap();
+    let request = create_request(&connection, &domain_name("fourteenth.com"), 1).unwrap();
     let auto_increment_value = current_auto_increment_value_responses(&connection).unwrap();
-    let creation = create_response(&connection, "fourteenth.com").unwrap();
+    let creation = create_response(&connection, request, &domain_name("fourteenth.com")).unwrap();
     assert!(creation == auto_increment_value as i32, "Insertion failed");
     let deletion = delete_response(&connection, format!("fourteenth.com")).unwrap();
     assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_request(&connection, format!("fourteenth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("fourteenth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
 }
 
 //fifteenth.com
 //
 #[test]
 fn test_15_add_and_remove_response_check_id () {
-    let connection = establish_connection();
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("fifteenth.com")).unwrap();
+    let request = create_request(&connection, &domain_name("fifteenth.com"), 1).unwrap();
     let auto_increment_value = current_auto_increment_value_responses(&connection).unwrap();
-    let creation = create_response(&connection, "fifteenth.com").unwrap();
+    let creation = create_response(&connection, request, &domain_name("fifteenth.com")).unwrap();
     assert!(creation == auto_increment_value as i32, "Insertion failed");
 
     let identification_value = get_latest_response_id(&connection).unwrap();
@@ -309,4 +433,867 @@ fn test_15_add_and_remove_response_check_id () {
 
     let deletion = delete_response(&connection, format!("fifteenth.com")).unwrap();
     assert!(deletion == 1, "Deletion failed");
-}
\ No newline at end of file
+    let deletion = delete_request(&connection, format!("fifteenth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("fifteenth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+//sixteenth.com
+//check that the sync checkpoint is saved and overwritten for a registry
+#[test]
+fn test_16_update_and_get_sync_state () {
+    let connection = establish_test_connection();
+    let registry = "0000000000000000000000000000000000000016";
+    assert!(get_sync_state(&connection, registry).unwrap().is_none(), "SyncState exists before it was saved");
+
+    update_sync_state(&connection, registry, 100, 3).unwrap();
+    update_sync_state(&connection, registry, 120, 0).unwrap();
+    let state = get_sync_state(&connection, registry).unwrap().expect("SyncState was not saved");
+    assert!(state.block_number == 120 && state.log_index == 0, "SyncState was not overwritten");
+}
+
+//seventeenth.com
+//check that pending applications are restored for the registry they were saved under
+#[test]
+fn test_17_save_load_and_delete_pending_applications () {
+    let connection = establish_test_connection();
+    let registry = "0000000000000000000000000000000000000017";
+    let creation = save_pending_application(&connection, registry, "17a", "seventeenth.com").unwrap();
+    assert!(creation == 1, "Insertion failed");
+    save_pending_application(&connection, "0000000000000000000000000000000000000000", "17b", "other.com").unwrap();
+
+    let applications = load_pending_applications(&connection, registry).unwrap();
+    assert!(applications.len() == 1, "PendingApplications from another registry were loaded");
+    assert_eq!(applications.get("17a"), Some(&format!("seventeenth.com")));
+
+    let deletion = delete_pending_application(&connection, registry, "17a").unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_pending_application(&connection, "0000000000000000000000000000000000000000", "17b").unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//eighteenth.com
+//check that processed logs can be found by position and rolled back from a block
+#[test]
+fn test_18_record_and_delete_processed_logs () {
+    let connection = establish_test_connection();
+    let registry = "0000000000000000000000000000000000000018";
+    for block in 1..4 {
+        let processed_log = NewProcessedLog {
+            registry: registry,
+            block_number: block,
+            log_index: 0,
+            block_hash: "18",
+            transaction_hash: "18",
+            listing_hash: "18",
+            action: "whitelisted",
+            domain: Some("eighteenth.com"),
+            previous_listing: None,
+        };
+        let creation = record_processed_log(&connection, &processed_log).unwrap();
+        assert!(creation == 1, "Insertion failed");
+    }
+
+    let processed = get_processed_log(&connection, registry, 2, 0).unwrap().expect("ProcessedLog was not recorded");
+    assert_eq!(processed.domain, Some(format!("eighteenth.com")));
+
+    let reverted = processed_logs_from(&connection, registry, 2).unwrap();
+    assert!(reverted.len() == 2 && reverted[0].block_number == 3, "ProcessedLogs were not returned newest first");
+
+    let deletion = delete_processed_logs_from(&connection, registry, 2).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+    let deletion = delete_processed_logs_from(&connection, registry, 0).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//nineteenth.com
+//check that rejected applications are recorded per registry and removed on rollback
+#[test]
+fn test_19_create_and_delete_rejected_applications () {
+    let connection = establish_test_connection();
+    let registry = "0000000000000000000000000000000000000019";
+    let rejected_application = NewRejectedApplication {
+        registry: registry,
+        listing_hash: "19",
+        data: "6e696e657465656e74682e636f6d",
+        block_number: 19,
+        log_index: 1,
+        transaction_hash: "19",
+    };
+    let creation = create_rejected_application(&connection, &rejected_application).unwrap();
+    assert!(creation == 1, "Insertion failed");
+
+    let rejected = get_rejected_applications(&connection, registry).unwrap();
+    assert!(rejected.len() == 1, "RejectedApplication was not recorded");
+    assert_eq!(rejected[0].data, format!("6e696e657465656e74682e636f6d"));
+
+    let deletion = delete_rejected_applications_from(&connection, registry, 20).unwrap();
+    assert!(deletion == 0, "Deletion removed a RejectedApplication before the block");
+    let deletion = delete_rejected_applications_from(&connection, registry, 19).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//twentieth.com
+//check that whitelisting and removing a listing moves its registry listing through the lifecycle
+#[test]
+fn test_20_registry_listing_follows_listing () {
+    let connection = establish_test_connection();
+    let creation = create_listing(&connection, &domain_name("twentieth.com")).unwrap();
+    assert!(creation == 1, "Insertion failed");
+
+    let registry_listing = get_registry_listing_by_domain(&connection, "twentieth.com").unwrap().expect("RegistryListing was not created");
+    assert_eq!(registry_listing.listing_hash, domain_listing_hash("twentieth.com"));
+    assert_eq!(registry_listing.status(), Some(ListingStatus::Whitelisted));
+
+    let deletion = delete_listing(&connection, format!("twentieth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let registry_listing = get_registry_listing_by_domain(&connection, "twentieth.com").unwrap().expect("RegistryListing was deleted");
+    assert_eq!(registry_listing.status(), Some(ListingStatus::Removed));
+    assert!(!is_whitelisted(&connection, &domain_name("twentieth.com")).unwrap(), "is_whitelisted returned true for a removed listing");
+
+    let deletion = delete_registry_listing(&connection, &registry_listing.listing_hash).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//twentyfirst.com
+//check that is_whitelisted only accepts listings in the whitelisted status
+#[test]
+fn test_21_is_whitelisted_follows_registry_listing_status () {
+    let connection = establish_test_connection();
+    let mut registry_listing = RegistryListing::new(&domain_listing_hash("twentyfirst.com"), "twentyfirst.com", ListingStatus::Challenged);
+    registry_listing.unstaked_deposit = format!("100000000000000000000");
+    save_registry_listing(&connection, &registry_listing).unwrap();
+    assert!(!is_whitelisted(&connection, &domain_name("twentyfirst.com")).unwrap(), "is_whitelisted returned true for a challenged listing");
+
+    let challenged = get_registry_listings_with_status(&connection, ListingStatus::Challenged).unwrap();
+    assert!(challenged.len() == 1, "Challenged listing was not returned");
+    assert_eq!(challenged[0].unstaked_deposit, format!("100000000000000000000"));
+
+    registry_listing.status = ListingStatus::Whitelisted.as_str().to_string();
+    save_registry_listing(&connection, &registry_listing).unwrap();
+    assert!(is_whitelisted(&connection, &domain_name("twentyfirst.com")).unwrap(), "is_whitelisted returned false for a whitelisted listing");
+
+    let deletion = delete_registry_listing(&connection, &registry_listing.listing_hash).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//twentysecond.com
+//check that challenges can be found by domain, resolved and reopened on rollback of their own registry only
+#[test]
+fn test_22_create_resolve_and_reopen_challenge () {
+    let connection = establish_test_connection();
+    let hash = domain_listing_hash("twentysecond.com");
+    let other_challenge = NewChallenge {
+        registry: "0000000000000000000000000000000000000022",
+        challenge_id: "22",
+        listing_hash: &hash,
+        deposit: "50000",
+        poll_id: "22",
+        data: "other registry",
+        outcome: ChallengeOutcome::Failed.as_str(),
+        block_number: 22,
+        transaction_hash: "22",
+    };
+    create_challenge(&connection, &other_challenge).unwrap();
+    resolve_challenge(&connection, "0000000000000000000000000000000000000022", "22", ChallengeOutcome::Failed, 30, "30").unwrap();
+    let new_challenge = NewChallenge {
+        registry: "22",
+        challenge_id: "22",
+        listing_hash: &hash,
+        deposit: "50000",
+        poll_id: "22",
+        data: "serves malware",
+        outcome: ChallengeOutcome::Open.as_str(),
+        block_number: 22,
+        transaction_hash: "22",
+    };
+    let creation = create_challenge(&connection, &new_challenge).unwrap();
+    assert!(creation == 1, "Insertion failed");
+
+    let challenges = challenges_for_domain(&connection, "twentysecond.com").unwrap();
+    assert!(challenges.len() == 2, "Challenges were not found by domain");
+    assert!(challenges.iter().any(|challenge| challenge.registry == "22" && challenge.data == "serves malware"), "Challenge was not found by domain");
+    assert!(open_challenges(&connection).unwrap().iter().any(|challenge| challenge.challenge_id == "22"), "Challenge was not open");
+
+    resolve_challenge(&connection, "22", "22", ChallengeOutcome::Failed, 30, "30").unwrap();
+    let challenge = get_challenge(&connection, "22", "22").unwrap().expect("Challenge was deleted");
+    assert_eq!(challenge.outcome(), Some(ChallengeOutcome::Failed));
+    assert!(!open_challenges(&connection).unwrap().iter().any(|challenge| challenge.challenge_id == "22"), "Resolved challenge was open");
+
+    let reopened = reopen_challenges_from(&connection, "22", 30).unwrap();
+    assert!(reopened == 1, "Challenge was not reopened");
+    assert_eq!(get_challenge(&connection, "22", "22").unwrap().unwrap().outcome(), Some(ChallengeOutcome::Open));
+    let other = get_challenge(&connection, "0000000000000000000000000000000000000022", "22").unwrap().expect("Challenge of the other registry was deleted");
+    assert_eq!(other.outcome(), Some(ChallengeOutcome::Failed));
+
+    let deletion = delete_challenges_from(&connection, "22", 22).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    assert!(get_challenge(&connection, "0000000000000000000000000000000000000022", "22").unwrap().is_some(), "Challenge of the other registry was deleted");
+    let deletion = delete_challenges_from(&connection, "0000000000000000000000000000000000000022", 22).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//twentythird.com
+//check that reward claims are recorded per challenge
+#[test]
+fn test_23_create_and_delete_reward_claims () {
+    let connection = establish_test_connection();
+    for index in 0..3 {
+        //the last claim is for a challenge with the same id on another registry
+        let new_reward_claim = NewRewardClaim {
+            registry: if index < 2 { "23" } else { "0000000000000000000000000000000000000023" },
+            challenge_id: "23",
+            voter: "0000000000000000000000000000000000000023",
+            reward: "1000",
+            block_number: 23,
+            log_index: index,
+            transaction_hash: "23",
+        };
+        let creation = create_reward_claim(&connection, &new_reward_claim).unwrap();
+        assert!(creation == 1, "Insertion failed");
+    }
+
+    let repeated = NewRewardClaim {
+        registry: "23",
+        challenge_id: "23",
+        voter: "0000000000000000000000000000000000000023",
+        reward: "1000",
+        block_number: 23,
+        log_index: 0,
+        transaction_hash: "23",
+    };
+    assert!(create_reward_claim(&connection, &repeated).is_err(), "RewardClaim was recorded twice for the same log");
+
+    let claims = reward_claims_for_challenge(&connection, "23", "23").unwrap();
+    assert!(claims.len() == 2, "RewardClaims were not recorded");
+    assert_eq!(claims[1].log_index, 1);
+
+    let deletion = delete_reward_claims_from(&connection, "23", 23).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+    assert!(reward_claims_for_challenge(&connection, "0000000000000000000000000000000000000023", "23").unwrap().len() == 1, "RewardClaim of the other registry was deleted");
+    let deletion = delete_reward_claims_from(&connection, "0000000000000000000000000000000000000023", 23).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//twentyfourth.com
+//check that the stake of a listing is returned with its deposit and withdrawal history
+#[test]
+fn test_24_get_listing_stake () {
+    let connection = establish_test_connection();
+    assert!(get_listing_stake(&connection, "24", "twentyfourth.com").unwrap().is_none(), "Stake returned for an unknown listing");
+
+    let hash = domain_listing_hash("twentyfourth.com");
+    let mut registry_listing = RegistryListing::new(&hash, "twentyfourth.com", ListingStatus::Whitelisted);
+    registry_listing.unstaked_deposit = format!("150");
+    save_registry_listing(&connection, &registry_listing).unwrap();
+
+    let changes = [(StakeChangeKind::Deposit, "100", "200"), (StakeChangeKind::Withdrawal, "50", "150")];
+    for (index, &(kind, amount, new_total)) in changes.iter().enumerate() {
+        let new_stake_change = NewStakeChange {
+            registry: "24",
+            listing_hash: &hash,
+            kind: kind.as_str(),
+            amount: amount,
+            new_total: new_total,
+            block_number: 24,
+            log_index: index as i64,
+            transaction_hash: "24",
+        };
+        let creation = create_stake_change(&connection, &new_stake_change).unwrap();
+        assert!(creation == 1, "Insertion failed");
+    }
+
+    let stake = get_listing_stake(&connection, "24", "twentyfourth.com").unwrap().expect("Stake was not found");
+    assert_eq!(stake.balance, format!("150"));
+    assert!(stake.history.len() == 2, "Stake history was not recorded");
+    assert_eq!(stake.history[0].kind(), Some(StakeChangeKind::Deposit));
+    assert_eq!(stake.history[1].kind(), Some(StakeChangeKind::Withdrawal));
+    assert_eq!(stake.history[1].new_total, format!("150"));
+    let other_stake = get_listing_stake(&connection, "0000000000000000000000000000000000000024", "twentyfourth.com").unwrap().expect("Stake was not found");
+    assert!(other_stake.history.is_empty(), "Stake changes of another registry were returned");
+
+    assert!(delete_stake_changes_from(&connection, "0000000000000000000000000000000000000024", 24).unwrap() == 0, "Stake changes of another registry were deleted");
+    let deletion = delete_stake_changes_from(&connection, "24", 24).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+    delete_registry_listing(&connection, &hash).unwrap();
+}
+
+//twentyfifth.com
+//check that listings can be managed through connections borrowed from the pool
+#[test]
+fn test_25_add_and_remove_ad_server_through_pool () {
+    //a single connection is handed out every time so an in-memory sqlite database is kept between borrows
+    let mut config = Config::load(None).unwrap();
+    config.pool_size = 1;
+    let pool = create_pool(&config).unwrap();
+
+    let connection = get_connection(&pool).unwrap();
+    run_migrations(&connection).unwrap();
+    let creation = create_listing(&connection, &domain_name("twentyfifth.com")).unwrap();
+    assert!(creation == 1, "Insertion failed");
+    drop(connection);
+
+    let connection = get_connection(&pool).unwrap();
+    assert!(is_whitelisted(&connection, &domain_name("twentyfifth.com")).unwrap(), "Listing added through the pool was not whitelisted");
+
+    let deletion = delete_listing(&connection, format!("twentyfifth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//twentysixth.com
+//check that every request made for a publisher gets the id of its own row
+#[test]
+fn test_26_create_request_returns_new_id () {
+    let connection = establish_test_connection();
+    let first = create_request(&connection, &domain_name("twentysixth.com"), 1).unwrap();
+    let second = create_request(&connection, &domain_name("twentysixth.com"), 2).unwrap();
+    assert!(second > first, "Second request did not get a new id");
+    assert!(second == get_latest_request_id(&connection).unwrap(), "ID value does not match the latest insertion into requests table");
+
+    create_listing(&connection, &domain_name("twentysixth.com")).unwrap();
+    let response_first = create_response(&connection, first, &domain_name("twentysixth.com")).unwrap();
+    let response_second = create_response(&connection, second, &domain_name("twentysixth.com")).unwrap();
+    assert!(response_second > response_first, "Second response did not get a new id");
+    assert!(response_second == get_latest_response_id(&connection).unwrap(), "ID value does not match the latest insertion into responses table");
+
+    let deletion = delete_response(&connection, format!("twentysixth.com")).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+    let deletion = delete_request(&connection, format!("twentysixth.com")).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("twentysixth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//twentyseventh.com
+//check that batches of requests and responses return every id
+#[test]
+fn test_27_create_requests_and_responses_in_batches () {
+    let connection = establish_test_connection();
+    let new_requests = vec![
+        (domain_name("twentyseventh.com"), 1),
+        (domain_name("twentyseventh.com"), 2),
+        (domain_name("twentyseventh.com"), 3),
+    ];
+    let ids = create_requests(&connection, &new_requests).unwrap();
+    assert!(ids.len() == 3 && ids[0] < ids[1] && ids[1] < ids[2], "Request ids were not returned in order");
+    assert!(ids[2] == get_latest_request_id(&connection).unwrap(), "ID value does not match the latest insertion into requests table");
+
+    create_listing(&connection, &domain_name("twentyseventh.com")).unwrap();
+    let ids = create_responses(&connection, &[(ids[0], domain_name("twentyseventh.com"))]).unwrap();
+    assert!(ids.len() == 1 && ids[0] == get_latest_response_id(&connection).unwrap(), "Response id was not returned");
+
+    let deletion = delete_response(&connection, format!("twentyseventh.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_request(&connection, format!("twentyseventh.com")).unwrap();
+    assert!(deletion == 3, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("twentyseventh.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//twentyeighth.com
+//check that deletes only ever remove rows for the one domain they name
+#[test]
+fn test_28_deletes_only_affect_the_named_domain () {
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("twentyeighth.com")).unwrap();
+    create_listing(&connection, &domain_name("atwentyeighth.com")).unwrap();
+    let request = create_request(&connection, &domain_name("twentyeighth.com"), 1).unwrap();
+    let other_request = create_request(&connection, &domain_name("atwentyeighth.com"), 1).unwrap();
+    create_response(&connection, request, &domain_name("twentyeighth.com")).unwrap();
+    create_response(&connection, other_request, &domain_name("atwentyeighth.com")).unwrap();
+
+    for pattern in ["%", "%twentyeighth.com", "_twentyeighth.com"] {
+        assert!(delete_listing(&connection, pattern.to_string()).is_err(), "Listings were deleted by a pattern");
+        assert!(delete_request(&connection, pattern.to_string()).is_err(), "Requests were deleted by a pattern");
+        assert!(delete_response(&connection, pattern.to_string()).is_err(), "Responses were deleted by a pattern");
+    }
+    assert!(is_whitelisted(&connection, &domain_name("twentyeighth.com")).unwrap(), "Listing was deleted by a pattern");
+    assert!(is_whitelisted(&connection, &domain_name("atwentyeighth.com")).unwrap(), "Listing was deleted by a pattern");
+
+    assert!(delete_response(&connection, format!("TwentyEighth.com")).unwrap() == 1, "Deletion failed");
+    assert!(delete_request(&connection, format!("TwentyEighth.com")).unwrap() == 1, "Deletion failed");
+    assert!(delete_listing(&connection, format!("TwentyEighth.com")).unwrap() == 1, "Deletion failed");
+    assert!(is_whitelisted(&connection, &domain_name("atwentyeighth.com")).unwrap(), "Deletion removed another listing");
+
+    assert!(delete_response(&connection, format!("atwentyeighth.com")).unwrap() == 1, "Deletion failed");
+    assert!(delete_request(&connection, format!("atwentyeighth.com")).unwrap() == 1, "Deletion failed");
+    assert!(delete_listing(&connection, format!("atwentyeighth.com")).unwrap() == 1, "Deletion failed");
+}
+
+//twentyninth.co.uk
+//check that hosts are matched to the most specific whitelisted listing at or below their registrable domain
+#[test]
+fn test_29_is_whitelisted_host_walks_up_to_the_registrable_domain () {
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("twentyninth.co.uk")).unwrap();
+    create_listing(&connection, &domain_name("eu.twentyninth.co.uk")).unwrap();
+
+    let host_match = is_whitelisted_host(&connection, "Bid.US-East.twentyninth.co.uk").unwrap().expect("Subdomain host was not whitelisted");
+    assert_eq!(host_match.host.as_str(), "bid.us-east.twentyninth.co.uk");
+    assert_eq!(host_match.listing.domain, format!("twentyninth.co.uk"));
+    assert_eq!(host_match.reason, HostMatchReason::RegistrableDomain);
+
+    let host_match = is_whitelisted_host(&connection, "bid.eu.twentyninth.co.uk").unwrap().expect("Subdomain host was not whitelisted");
+    assert_eq!(host_match.listing.domain, format!("eu.twentyninth.co.uk"));
+    assert_eq!(host_match.reason, HostMatchReason::ParentDomain);
+
+    let host_match = is_whitelisted_host(&connection, "twentyninth.co.uk").unwrap().expect("Listed host was not whitelisted");
+    assert_eq!(host_match.reason, HostMatchReason::Exact);
+
+    assert!(is_whitelisted_host(&connection, "nottwentyninth.co.uk").unwrap().is_none(), "Unlisted host was whitelisted");
+    assert!(is_whitelisted_host(&connection, "co.uk").unwrap().is_none(), "Public suffix was whitelisted");
+    match is_whitelisted_host(&connection, "bid_twentyninth.co.uk") {
+        Err(Error::InvalidDomain(_)) => {},
+        _ => panic!("Improper host was accepted"),
+    }
+
+    //a listing for a platform does not cover the sites hosted on it
+    create_listing(&connection, &domain_name("twentyninth.github.io")).unwrap();
+    create_listing(&connection, &domain_name("github.io")).unwrap();
+    assert!(is_whitelisted_host(&connection, "other.github.io").unwrap().is_none(), "Site under a public suffix was whitelisted");
+    let host_match = is_whitelisted_host(&connection, "bid.twentyninth.github.io").unwrap().expect("Subdomain host was not whitelisted");
+    assert_eq!(host_match.listing.domain, format!("twentyninth.github.io"));
+
+    for name in ["twentyninth.co.uk", "eu.twentyninth.co.uk", "twentyninth.github.io", "github.io"] {
+        let deletion = delete_listing(&connection, name.to_string()).unwrap();
+        assert!(deletion == 1, "Deletion failed");
+    }
+    assert!(is_whitelisted_host(&connection, "bid.twentyninth.co.uk").unwrap().is_none(), "Host of a deleted listing was whitelisted");
+}
+
+//thirtieth.com
+//check that the whitelist cache answers from memory, follows changes sent to it and counts hits and misses
+#[test]
+fn test_30_whitelist_cache_follows_changes () {
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("thirtieth.com")).unwrap();
+
+    let cache = WhitelistCache::load(&connection).unwrap();
+    assert!(cache.is_whitelisted(&domain_name("Thirtieth.com")), "Cached listing was not whitelisted");
+    assert!(!cache.is_whitelisted(&domain_name("notthirtieth.com")), "Unlisted domain was whitelisted");
+    assert!(cache.hits() == 1 && cache.misses() == 1, "Lookups were not counted");
+
+    cache.apply(&WhitelistChange::Removed(domain_name("thirtieth.com")));
+    assert!(!cache.is_whitelisted(&domain_name("thirtieth.com")), "Removed listing was still whitelisted");
+    cache.refresh(&connection).unwrap();
+    assert!(cache.is_whitelisted(&domain_name("thirtieth.com")), "Refresh did not restore the listing");
+
+    //changes sent by the sync reach a cache kept up to date in the background
+    let mut config = Config::load(None).unwrap();
+    config.pool_size = 1;
+    let pool = create_pool(&config).unwrap();
+    let cache = Arc::new(cache);
+    let (sender, receiver) = channel();
+    keep_up_to_date(cache.clone(), pool, receiver, Duration::from_secs(3600));
+    sender.send(WhitelistChange::Added(domain_name("sync.thirtieth.com"))).unwrap();
+    for _ in 0..100 {
+        if cache.len() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(cache.is_whitelisted(&domain_name("sync.thirtieth.com")), "Change sent to the cache was not applied");
+
+    let deletion = delete_listing(&connection, format!("thirtieth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//thirtyfirst.com
+//check that the HTTP API answers from the database
+#[test]
+fn test_31_http_api_serves_listings_and_latest_ids () {
+    //a single connection is handed out every time so an in-memory sqlite database is kept between borrows
+    let mut config = Config::load(None).unwrap();
+    config.pool_size = 1;
+    let pool = create_pool(&config).unwrap();
+
+    let connection = get_connection(&pool).unwrap();
+    run_migrations(&connection).unwrap();
+    let creation = create_listing(&connection, &domain_name("thirtyfirst.com")).unwrap();
+    assert!(creation == 1, "Insertion failed");
+    let request_id = create_request(&connection, &domain_name("thirtyfirst.com"), 1).unwrap();
+    drop(connection);
+
+    let server = serve(&pool, config.registry_address(), "127.0.0.1:0", 1).unwrap();
+    let address = server.address();
+
+    let (status, body) = http_get(address, "/whitelisted/Thirtyfirst.com");
+    assert!(status.contains("200"), "Whitelisted lookup failed: {}", status);
+    assert!(body.contains("\"whitelisted\":true") && body.contains("\"domain\":\"thirtyfirst.com\""), "Listing was not whitelisted: {}", body);
+
+    let (status, body) = http_get(address, "/whitelisted/notthirtyfirst.com");
+    assert!(status.contains("200") && body.contains("\"whitelisted\":false"), "Unlisted domain was whitelisted: {}", body);
+
+    let (status, _) = http_get(address, "/whitelisted/thirty_first.com");
+    assert!(status.contains("400"), "Invalid domain was not rejected: {}", status);
+
+    let (status, body) = http_get(address, "/listings");
+    assert!(status.contains("200") && body.contains("\"thirtyfirst.com\""), "Listing was not listed: {}", body);
+
+    let (status, body) = http_get(address, "/listings/thirtyfirst.com");
+    assert!(status.contains("200") && body.contains("\"stake\"") && body.contains("\"challenges\":[]"), "Listing details were not served: {}", body);
+
+    let (status, _) = http_get(address, "/listings/notthirtyfirst.com");
+    assert!(status.contains("404"), "Unlisted domain was found: {}", status);
+
+    let (status, body) = http_get(address, "/requests/latest");
+    assert!(status.contains("200") && body.contains(&format!("\"id\":{}", request_id)), "Latest request id was not served: {}", body);
+
+    let (status, _) = http_get(address, "/unknown");
+    assert!(status.contains("404"), "Unknown endpoint was found: {}", status);
+
+    let connection = get_connection(&pool).unwrap();
+    let deletion = delete_request(&connection, format!("thirtyfirst.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("thirtyfirst.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//thirtysecond.com
+//check that pruning keeps only the most recent requests and responses
+#[test]
+fn test_32_prune_requests_and_responses () {
+    let connection = establish_test_connection();
+    assert!(!applied_migrations(&connection).unwrap().is_empty(), "Migrations were not listed");
+
+    let request_ids = create_requests(&connection, &[(domain_name("thirtysecond.com"), 1), (domain_name("thirtysecond.com"), 2), (domain_name("thirtysecond.com"), 3)]).unwrap();
+    match (prune_requests(&connection, -1), prune_responses(&connection, -1)) {
+        (Err(Error::Config(_)), Err(Error::Config(_))) => {},
+        _ => panic!("Negative number of rows to keep was accepted"),
+    };
+    create_listing(&connection, &domain_name("thirtysecond.com")).unwrap();
+    let answer_to_pruned = create_response(&connection, request_ids[0], &domain_name("thirtysecond.com")).unwrap();
+    let (pruned, responses_pruned) = prune_requests(&connection, 1).unwrap();
+    assert!(pruned >= 2, "Requests were not pruned");
+    assert!(responses_pruned >= 1 && get_response(&connection, answer_to_pruned).unwrap().is_none(), "Response to a pruned request was left behind");
+    assert!(get_latest_request_id(&connection).unwrap() == request_ids[2], "Most recent request was pruned");
+
+    let response_ids = create_responses(&connection, &[(request_ids[2], domain_name("thirtysecond.com")), (request_ids[2], domain_name("thirtysecond.com"))]).unwrap();
+    let pruned = prune_responses(&connection, 5).unwrap();
+    assert!(pruned == 0, "Responses within the kept number were pruned");
+    let pruned = prune_responses(&connection, 0).unwrap();
+    assert!(pruned >= 2, "Responses were not pruned");
+    assert!(get_latest_response_id(&connection).is_err(), "Responses remained after pruning all of them");
+    assert!(response_ids.len() == 2, "Batch insertion failed");
+
+    let deletion = delete_request(&connection, format!("thirtysecond.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("thirtysecond.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//thirtythird.com
+//check that responses are recorded against the request they answer
+#[test]
+fn test_33_responses_for_request_and_unanswered_requests () {
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("adserver.thirtythird.com")).unwrap();
+    let answered = create_request(&connection, &domain_name("thirtythird.com"), 1).unwrap();
+    let unanswered = create_request(&connection, &domain_name("thirtythird.com"), 2).unwrap();
+
+    let first = create_response(&connection, answered, &domain_name("adserver.thirtythird.com")).unwrap();
+    let second = create_response(&connection, answered, &domain_name("AdServer.thirtythird.com")).unwrap();
+    let responses = responses_for_request(&connection, answered).unwrap();
+    assert!(responses.iter().map(|response| response.id).collect::<Vec<i32>>() == vec![first, second], "Responses to the request were not returned in order");
+    assert!(responses[0].publisher == "thirtythird.com", "Response was not made for the publisher of the request");
+    assert!(responses[0].ad_server == Some(format!("adserver.thirtythird.com")), "Ad server of the response was not recorded");
+    assert!(responses_for_request(&connection, unanswered).unwrap().is_empty(), "Unanswered request had responses");
+
+    let since = get_request(&connection, answered).unwrap().unwrap().created_at;
+    let requests = unanswered_requests(&connection, since).unwrap();
+    assert!(requests.len() == 1 && requests[0].id == unanswered, "Unanswered requests were not found");
+    let after = get_request(&connection, unanswered).unwrap().unwrap().created_at + TimeDelta::seconds(1);
+    assert!(unanswered_requests(&connection, after).unwrap().is_empty(), "Requests before since were returned");
+
+    match create_response(&connection, unanswered + 1, &domain_name("adserver.thirtythird.com")) {
+        Err(Error::NotFound(_)) => {},
+        _ => panic!("Response to a request that does not exist was accepted"),
+    };
+    match create_response(&connection, unanswered, &domain_name("unlisted.thirtythird.com")) {
+        Err(Error::NotFound(_)) => {},
+        _ => panic!("Response from an ad server that is not listed was accepted"),
+    };
+
+    //a response keeps its ad server after the listing is removed
+    let deletion = delete_listing(&connection, format!("adserver.thirtythird.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    assert!(get_response(&connection, first).unwrap().unwrap().ad_server == Some(format!("adserver.thirtythird.com")), "Ad server was erased with the listing");
+
+    let deletion = delete_response(&connection, format!("thirtythird.com")).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+    let deletion = delete_request(&connection, format!("thirtythird.com")).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+}
+
+//thirtyfourth.com
+//check that an OpenRTB bid request is stored with its details
+#[test]
+fn test_34_ingest_openrtb_request () {
+    let connection = establish_test_connection();
+    let bid_request = r#"{
+        "id": "thirtyfourth-auction",
+        "imp": [{"id": "1", "banner": {"w": 728, "h": 90}, "bidfloor": 0.25}],
+        "site": {"domain": "ThirtyFourth.com", "page": "https://thirtyfourth.com/news"},
+        "device": {"devicetype": 4, "geo": {"country": "CAN", "region": "ON"}},
+        "user": {"id": "thirtyfourth-user"},
+        "ext": {"userquality": 3}
+    }"#;
+    let request = ingest_openrtb_request(&connection, bid_request).unwrap();
+    assert!(request == get_latest_request_id(&connection).unwrap(), "ID value does not match the latest insertion into requests table");
+
+    let stored = get_request(&connection, request).unwrap().unwrap();
+    assert!(stored.publisher == "thirtyfourth.com" && stored.userquality == 3, "Publisher and user quality were not stored");
+    assert!(stored.received_at.is_some(), "Received time was not stored");
+    assert!(stored.auction_id == Some(format!("thirtyfourth-auction")), "Auction id was not stored");
+    assert!(stored.ad_format == Some(format!("banner")) && stored.ad_size == Some(format!("728x90")), "Ad format was not stored");
+    assert!(stored.bid_floor_micros == Some(250000) && stored.bid_floor_currency == Some(format!("USD")), "Bid floor was not stored");
+    assert!(stored.page_url == Some(format!("https://thirtyfourth.com/news")), "Page URL was not stored");
+    assert!(stored.device_type == Some(4) && stored.region == Some(format!("CAN-ON")), "Device was not stored");
+    assert!(stored.user_id_hash.is_some(), "User id hash was not stored");
+
+    //plain requests have only their publisher, user quality and received time
+    let plain = create_request(&connection, &domain_name("thirtyfourth.com"), 1).unwrap();
+    let stored = get_request(&connection, plain).unwrap().unwrap();
+    assert!(stored.received_at.is_some() && stored.auction_id.is_none(), "Plain request got OpenRTB details");
+
+    assert!(ingest_openrtb_request(&connection, "{}").is_err(), "Improper bid request was stored");
+
+    let deletion = delete_request(&connection, format!("thirtyfourth.com")).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+}
+
+//thirtyfifth.com
+//check that OpenRTB bid responses are stored against their request and updated by win and loss notices
+#[test]
+fn test_35_ingest_openrtb_response_and_record_outcome () {
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("adserver.thirtyfifth.com")).unwrap();
+    let request = ingest_openrtb_request(&connection, r#"{"id": "thirtyfifth-auction", "imp": [{"id": "1", "banner": {"w": 300, "h": 250}}], "site": {"domain": "thirtyfifth.com"}}"#).unwrap();
+
+    let bid_response = r#"{"id": "thirtyfifth-auction", "seatbid": [{"seat": "35", "bid": [{"id": "1", "impid": "1", "price": 1.75, "crid": "creative-35"}, {"id": "2", "impid": "1", "price": 0.5}]}]}"#;
+    let ids = ingest_openrtb_response(&connection, &domain_name("adserver.thirtyfifth.com"), bid_response, 40).unwrap();
+    assert!(ids.len() == 2, "A response was not stored for every bid");
+    assert!(responses_for_request(&connection, request).unwrap().len() == 2, "Responses were not linked to the request of the auction");
+
+    let stored = get_response(&connection, ids[0]).unwrap().unwrap();
+    assert!(stored.publisher == "thirtyfifth.com" && stored.ad_server == Some(format!("adserver.thirtyfifth.com")), "Publisher and ad server were not stored");
+    assert!(stored.seat == Some(format!("35")) && stored.creative_id == Some(format!("creative-35")), "Bid was not stored");
+    assert!(stored.bid_price_micros == Some(1750000) && stored.bid_currency == Some(format!("USD")), "Bid price was not stored");
+    assert!(stored.latency_ms == Some(40) && stored.won.is_none(), "Response was stored with an outcome");
+
+    record_win(&connection, ids[0], 1200000).unwrap();
+    record_loss(&connection, ids[1], Some(102)).unwrap();
+    let winner = get_response(&connection, ids[0]).unwrap().unwrap();
+    assert!(winner.won == Some(true) && winner.clearing_price_micros == Some(1200000), "Win was not recorded");
+    let loser = get_response(&connection, ids[1]).unwrap().unwrap();
+    assert!(loser.won == Some(false) && loser.loss_reason == Some(102) && loser.clearing_price_micros.is_none(), "Loss was not recorded");
+
+    match record_win(&connection, ids[1] + 1, 1) {
+        Err(Error::NotFound(_)) => {},
+        _ => panic!("Win was recorded for a response that does not exist"),
+    };
+    match ingest_openrtb_response(&connection, &domain_name("adserver.thirtyfifth.com"), r#"{"id": "unknown-auction"}"#, 40) {
+        Err(Error::NotFound(_)) => {},
+        _ => panic!("Response to an unknown auction was stored"),
+    };
+
+    let deletion = delete_response(&connection, format!("thirtyfifth.com")).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+    let deletion = delete_request(&connection, format!("thirtyfifth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("adserver.thirtyfifth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//thirtysixth.com
+//check that requests and responses are found by when they were created, a page at a time
+#[test]
+fn test_36_requests_and_responses_between () {
+    let connection = establish_test_connection();
+    let before = Utc::now().naive_utc() - TimeDelta::seconds(1);
+    create_listing(&connection, &domain_name("adserver.thirtysixth.com")).unwrap();
+    let request_ids = create_requests(&connection, &[(domain_name("thirtysixth.com"), 1), (domain_name("thirtysixth.com"), 2), (domain_name("thirtysixth.com"), 3)]).unwrap();
+    create_request(&connection, &domain_name("other.thirtysixth.com"), 4).unwrap();
+    let response_ids = create_responses(&connection, &[(request_ids[0], domain_name("adserver.thirtysixth.com")), (request_ids[1], domain_name("adserver.thirtysixth.com"))]).unwrap();
+    let after = Utc::now().naive_utc() + TimeDelta::seconds(1);
+    let publisher = domain_name("thirtysixth.com");
+
+    let first = requests_between(&connection, before, after, Some(&publisher), Page::first(2)).unwrap();
+    assert!(first.iter().map(|request| request.id).collect::<Vec<i32>>() == request_ids[..2].to_vec(), "First page of requests was not returned oldest first");
+    assert!(first.iter().all(|request| request.created_at >= before && request.created_at < after), "Creation time was not stored");
+    let second = requests_between(&connection, before, after, Some(&publisher), Page::first(2).next()).unwrap();
+    assert!(second.len() == 1 && second[0].id == request_ids[2], "Second page of requests was not returned");
+    assert!(count_requests_between(&connection, before, after, Some(&publisher)).unwrap() == 3, "Requests of the publisher were not counted");
+    assert!(count_requests_between(&connection, before, after, None).unwrap() >= 4, "Requests of every publisher were not counted");
+    assert!(count_requests_between(&connection, after, after + TimeDelta::days(1), Some(&publisher)).unwrap() == 0, "Requests outside the window were counted");
+
+    let responses = responses_between(&connection, before, after, Some(&publisher), Page::first(10)).unwrap();
+    assert!(responses.iter().map(|response| response.id).collect::<Vec<i32>>() == response_ids, "Responses were not returned oldest first");
+    assert!(count_responses_between(&connection, before, after, Some(&publisher)).unwrap() == 2, "Responses of the publisher were not counted");
+    assert!(responses_between(&connection, before - TimeDelta::days(1), before, Some(&publisher), Page::first(10)).unwrap().is_empty(), "Responses outside the window were returned");
+
+    let deletion = delete_response(&connection, format!("thirtysixth.com")).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+    let deletion = delete_request(&connection, format!("thirtysixth.com")).unwrap();
+    assert!(deletion == 3, "Deletion failed");
+    let deletion = delete_request(&connection, format!("other.thirtysixth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("adserver.thirtysixth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//thirtyseventh.com
+//check that retention archives and deletes old requests with their responses and old responses, a chunk at a time
+#[test]
+fn test_37_retention_archives_and_deletes_old_rows () {
+    use acbidder_database::schema::{requests, responses};
+    use diesel::{ExpressionMethods, QueryDsl};
+
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("adserver.thirtyseventh.com")).unwrap();
+    let old_requests = create_requests(&connection, &[(domain_name("thirtyseventh.com"), 1), (domain_name("thirtyseventh.com"), 2), (domain_name("thirtyseventh.com"), 3)]).unwrap();
+    let recent_request = create_request(&connection, &domain_name("thirtyseventh.com"), 4).unwrap();
+    //more answers to one old request than fit in a chunk
+    let answers_to_old = create_responses(&connection, &[(old_requests[0], domain_name("adserver.thirtyseventh.com")), (old_requests[0], domain_name("adserver.thirtyseventh.com")), (old_requests[0], domain_name("adserver.thirtyseventh.com"))]).unwrap();
+    let old_answer_to_recent = create_response(&connection, recent_request, &domain_name("adserver.thirtyseventh.com")).unwrap();
+    let recent_answer = create_response(&connection, recent_request, &domain_name("adserver.thirtyseventh.com")).unwrap();
+    let long_ago = Utc::now().naive_utc() - TimeDelta::days(100);
+    diesel::update(requests::table.filter(requests::id.eq_any(&old_requests))).set(requests::created_at.eq(long_ago)).execute(&connection).unwrap();
+    diesel::update(responses::table.filter(responses::id.eq(old_answer_to_recent))).set(responses::created_at.eq(long_ago)).execute(&connection).unwrap();
+
+    let archive_dir = std::env::temp_dir().join(format!("acbidder-retention-{}", process::id()));
+    let policy = RetentionPolicy {
+        max_age: TimeDelta::days(90),
+        chunk_size: 2,
+        archive_dir: Some(archive_dir.clone()),
+    };
+    let report = apply_retention(&connection, &policy).unwrap();
+    assert!(report.requests_deleted == 3, "Old requests were not deleted");
+    assert!(report.responses_deleted == 4, "Old responses and responses to old requests were not deleted");
+    assert!(old_requests.iter().all(|request| get_request(&connection, *request).unwrap().is_none()), "Old request remained");
+    assert!(get_request(&connection, recent_request).unwrap().is_some(), "Recent request was deleted");
+    assert!(answers_to_old.iter().all(|response| get_response(&connection, *response).unwrap().is_none()), "Response to an old request remained");
+    assert!(get_response(&connection, old_answer_to_recent).unwrap().is_none(), "Old response remained");
+    assert!(get_response(&connection, recent_answer).unwrap().is_some(), "Recent response was deleted");
+
+    assert!(report.archives.len() == 2, "An archive was not written for both tables");
+    let archived: Vec<Vec<String>> = report.archives.iter().map(|path| {
+        BufReader::new(GzDecoder::new(fs::File::open(path).unwrap())).lines().map(|line| line.unwrap()).collect()
+    }).collect();
+    assert!(archived[0].len() == 3 && archived[0][0].contains("thirtyseventh.com"), "Requests were not archived");
+    assert!(archived[1].len() == 4, "Responses were not archived");
+    let second = apply_retention(&connection, &policy).unwrap();
+    assert!(second.requests_deleted == 0 && second.archives.is_empty(), "Retention deleted rows it had already deleted");
+    fs::remove_dir_all(&archive_dir).unwrap();
+
+    let deletion = delete_response(&connection, format!("thirtyseventh.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_request(&connection, format!("thirtyseventh.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_listing(&connection, format!("adserver.thirtyseventh.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//thirtyeighth.com
+//check that is_whitelisted, the whitelist cache and the API agree on the registry listing status after a removal and a rollback
+#[test]
+fn test_38_whitelist_readers_agree_after_removal_and_rollback () {
+    use acbidder_database::api::handle;
+
+    let connection = establish_test_connection();
+    let cache = WhitelistCache::new();
+    let agree = |expected: bool| {
+        cache.refresh(&connection).unwrap();
+        let listed = handle(&connection, "38", "/listings").body.to_string().contains("\"thirtyeighth.com\"");
+        assert!(is_whitelisted(&connection, &domain_name("thirtyeighth.com")).unwrap() == expected, "is_whitelisted disagreed");
+        assert!(cache.is_whitelisted(&domain_name("thirtyeighth.com")) == expected, "Cache disagreed");
+        assert!(listed == expected, "API disagreed");
+    };
+    create_listing(&connection, &domain_name("thirtyeighth.com")).unwrap();
+    agree(true);
+
+    let deletion = delete_listing(&connection, format!("thirtyeighth.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    agree(false);
+
+    //rolling back the removal restores the status the registry listing had before it
+    let mut registry_listing = get_registry_listing_by_domain(&connection, "thirtyeighth.com").unwrap().unwrap();
+    registry_listing.status = ListingStatus::Whitelisted.as_str().to_string();
+    save_registry_listing(&connection, &registry_listing).unwrap();
+    agree(true);
+
+    registry_listing.status = ListingStatus::Challenged.as_str().to_string();
+    save_registry_listing(&connection, &registry_listing).unwrap();
+    agree(false);
+
+    let deletion = delete_registry_listing(&connection, &registry_listing.listing_hash).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}
+
+//thirtyninth.com
+//check that a listing whitelisted by two differently written applications stays until neither is whitelisted
+#[test]
+fn test_39_shared_listing_kept_while_whitelisted () {
+    let connection = establish_test_connection();
+    create_listing(&connection, &domain_name("thirtyninth.com")).unwrap();
+    let mut first = get_registry_listing_by_domain(&connection, "thirtyninth.com").unwrap().unwrap();
+    let second = RegistryListing::new(&domain_listing_hash("ThirtyNinth.com"), "thirtyninth.com", ListingStatus::Whitelisted);
+    save_registry_listing(&connection, &second).unwrap();
+
+    let deletion = delete_registry_listing_domain(&connection, format!("thirtyninth.com"), &first.listing_hash).unwrap();
+    assert!(deletion == 0, "Listing shared with a whitelisted registry listing was deleted");
+    let deletion = delete_listing(&connection, format!("thirtyninth.com")).unwrap();
+    assert!(deletion == 0, "Listing shared with a whitelisted registry listing was deleted");
+    assert!(is_whitelisted(&connection, &domain_name("thirtyninth.com")).unwrap(), "Shared listing was not whitelisted");
+
+    first.status = ListingStatus::Removed.as_str().to_string();
+    save_registry_listing(&connection, &first).unwrap();
+    let deletion = delete_registry_listing_domain(&connection, format!("thirtyninth.com"), &second.listing_hash).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+
+    let deletion = delete_registry_listing(&connection, &first.listing_hash).unwrap() + delete_registry_listing(&connection, &second.listing_hash).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+}
+
+//fortieth.com
+//check that whitelisting a registry listing changes only the listing with its hash, not a rejected one with the same domain
+#[test]
+fn test_40_listing_status_follows_listing_hash () {
+    use acbidder_database::get_registry_listing;
+
+    let connection = establish_test_connection();
+    let spoof = RegistryListing::new(&format!("{:064}", 40), "fortieth.com", ListingStatus::Rejected);
+    save_registry_listing(&connection, &spoof).unwrap();
+    let hash = domain_listing_hash("Fortieth.com");
+    create_registry_listing_domain(&connection, &domain_name("fortieth.com"), &hash).unwrap();
+    assert!(get_registry_listing(&connection, &hash).unwrap().unwrap().status == ListingStatus::Whitelisted.as_str(), "Registry listing with the hash was not whitelisted");
+    assert!(get_registry_listing(&connection, &spoof.listing_hash).unwrap().unwrap().status == ListingStatus::Rejected.as_str(), "Rejected registry listing of the same domain was whitelisted");
+
+    let deletion = delete_registry_listing_domain(&connection, format!("fortieth.com"), &hash).unwrap();
+    assert!(deletion == 1, "Listing was kept for a rejected registry listing");
+
+    let deletion = delete_registry_listing(&connection, &spoof.listing_hash).unwrap() + delete_registry_listing(&connection, &hash).unwrap();
+    assert!(deletion == 2, "Deletion failed");
+}
+
+//fortyfirst.com
+//check that running the migrations whitelists a registry listing for a listed domain that has none
+#[test]
+fn test_41_migrations_backfill_registry_listings () {
+    use acbidder_database::schema::listings;
+    use diesel::ExpressionMethods;
+
+    let connection = establish_test_connection();
+    diesel::insert_into(listings::table).values(listings::domain.eq("fortyfirst.com")).execute(&connection).unwrap();
+    assert!(!is_whitelisted(&connection, &domain_name("fortyfirst.com")).unwrap(), "Listing without a registry listing was whitelisted");
+
+    run_migrations(&connection).unwrap();
+    assert!(is_whitelisted(&connection, &domain_name("fortyfirst.com")).unwrap(), "Listing was not backfilled");
+    let registry_listing = get_registry_listing_by_domain(&connection, "fortyfirst.com").unwrap().unwrap();
+    assert_eq!(registry_listing.listing_hash, domain_listing_hash("fortyfirst.com"));
+
+    let deletion = delete_listing(&connection, format!("fortyfirst.com")).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+    let deletion = delete_registry_listing(&connection, &registry_listing.listing_hash).unwrap();
+    assert!(deletion == 1, "Deletion failed");
+}